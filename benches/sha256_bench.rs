@@ -1,17 +1,43 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use fs_guard::sha256::{sha256, simd_sha256};
+use fs_guard::sha256::{portable_sha256, sha256, sha256_many, simd_sha256, Sha256};
 
 fn benchmark_sha256(c: &mut Criterion) {
     let data = vec![0u8; 1024]; // Example input data
 
     c.bench_function("SHA-256 without SIMD", |b| {
-        b.iter(|| sha256(black_box(&data)))
+        b.iter(|| portable_sha256(black_box(&data)))
     });
 
-    c.bench_function("SHA-256 with SIMD", |b| {
-        b.iter(|| simd_sha256(black_box(&data)))
+    if simd_sha256(&data).is_some() {
+        c.bench_function("SHA-256 with SIMD", |b| {
+            b.iter(|| simd_sha256(black_box(&data)))
+        });
+    }
+
+    c.bench_function("SHA-256 streaming", |b| {
+        b.iter(|| {
+            let mut hasher = Sha256::new();
+            for piece in black_box(&data).chunks(100) {
+                hasher.update(piece);
+            }
+            hasher.finalize()
+        })
+    });
+}
+
+fn benchmark_sha256_many(c: &mut Criterion) {
+    // A level of Merkle parents: 1024 independent 64-byte inputs
+    let data = vec![0u8; 64 * 1024];
+    let inputs: Vec<&[u8]> = data.chunks(64).collect();
+
+    c.bench_function("SHA-256 64-byte inputs one by one", |b| {
+        b.iter(|| black_box(&inputs).iter().map(|input| sha256(input)).collect::<Vec<_>>())
+    });
+
+    c.bench_function("SHA-256 64-byte inputs batched", |b| {
+        b.iter(|| sha256_many(black_box(&inputs)))
     });
 }
 
-criterion_group!(benches, benchmark_sha256);
+criterion_group!(benches, benchmark_sha256, benchmark_sha256_many);
 criterion_main!(benches);
@@ -0,0 +1,292 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::blake3::Blake3;
+use crate::merkle::HashFunction;
+use crate::sha256::{Sha224, Sha256};
+use crate::sha3::{Sha3_224, Sha3_256, Sha3_384, Sha3_512, Shake128, Shake256};
+use crate::sha512::{Sha384, Sha512, Sha512_256};
+#[cfg(feature = "std")]
+use std::io;
+
+/// Hash algorithms that can be selected at runtime, e.g. from a manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+    Sha512_256,
+    Sha3_224,
+    Sha3_256,
+    Sha3_384,
+    Sha3_512,
+    Shake128,
+    Shake256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Every supported algorithm, in the order they are listed to users.
+    pub const ALL: [HashAlgorithm; 12] = [
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha224,
+        HashAlgorithm::Sha384,
+        HashAlgorithm::Sha512,
+        HashAlgorithm::Sha512_256,
+        HashAlgorithm::Sha3_224,
+        HashAlgorithm::Sha3_256,
+        HashAlgorithm::Sha3_384,
+        HashAlgorithm::Sha3_512,
+        HashAlgorithm::Shake128,
+        HashAlgorithm::Shake256,
+        HashAlgorithm::Blake3,
+    ];
+
+    /// The name used for the algorithm in manifests and on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha224 => "sha224",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha384 => "sha384",
+            HashAlgorithm::Sha512 => "sha512",
+            HashAlgorithm::Sha512_256 => "sha512-256",
+            HashAlgorithm::Sha3_224 => "sha3-224",
+            HashAlgorithm::Sha3_256 => "sha3-256",
+            HashAlgorithm::Sha3_384 => "sha3-384",
+            HashAlgorithm::Sha3_512 => "sha3-512",
+            HashAlgorithm::Shake128 => "shake128",
+            HashAlgorithm::Shake256 => "shake256",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// Size of the digest in bytes.
+    ///
+    /// The SHAKE functions are used with a fixed output matching their
+    /// security level: 32 bytes for SHAKE128 and 64 bytes for SHAKE256.
+    pub fn output_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha224 => 28,
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha384 => 48,
+            HashAlgorithm::Sha512 => 64,
+            HashAlgorithm::Sha512_256 => 32,
+            HashAlgorithm::Sha3_224 => 28,
+            HashAlgorithm::Sha3_256 => 32,
+            HashAlgorithm::Sha3_384 => 48,
+            HashAlgorithm::Sha3_512 => 64,
+            HashAlgorithm::Shake128 => 32,
+            HashAlgorithm::Shake256 => 64,
+            HashAlgorithm::Blake3 => 32,
+        }
+    }
+}
+
+impl HashAlgorithm {
+    /// Creates a streaming hasher for the algorithm.
+    pub fn hasher(&self) -> Hasher {
+        let state = match self {
+            HashAlgorithm::Sha224 => State::Sha224(Sha224::new()),
+            HashAlgorithm::Sha256 => State::Sha256(Sha256::new()),
+            HashAlgorithm::Sha384 => State::Sha384(Sha384::new()),
+            HashAlgorithm::Sha512 => State::Sha512(Sha512::new()),
+            HashAlgorithm::Sha512_256 => State::Sha512_256(Sha512_256::new()),
+            HashAlgorithm::Sha3_224 => State::Sha3_224(Sha3_224::new()),
+            HashAlgorithm::Sha3_256 => State::Sha3_256(Sha3_256::new()),
+            HashAlgorithm::Sha3_384 => State::Sha3_384(Sha3_384::new()),
+            HashAlgorithm::Sha3_512 => State::Sha3_512(Sha3_512::new()),
+            HashAlgorithm::Shake128 => State::Shake128(Shake128::new()),
+            HashAlgorithm::Shake256 => State::Shake256(Shake256::new()),
+            HashAlgorithm::Blake3 => State::Blake3(Box::default()),
+        };
+        Hasher { algorithm: *self, state }
+    }
+
+    /// Hashes everything read from `reader` through a fixed-size buffer.
+    ///
+    /// # Returns
+    ///
+    /// The digest, `output_len()` bytes long, or the first read error.
+    #[cfg(feature = "std")]
+    pub fn hash_reader<R: io::Read>(&self, mut reader: R) -> io::Result<Vec<u8>> {
+        let mut hasher = self.hasher();
+        io::copy(&mut reader, &mut hasher)?;
+        Ok(hasher.finalize())
+    }
+}
+
+/// Streaming hasher for an algorithm chosen at runtime.
+#[derive(Clone)]
+pub struct Hasher {
+    algorithm: HashAlgorithm,
+    state: State,
+}
+
+#[derive(Clone)]
+enum State {
+    Sha224(Sha224),
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+    Sha512_256(Sha512_256),
+    Sha3_224(Sha3_224),
+    Sha3_256(Sha3_256),
+    Sha3_384(Sha3_384),
+    Sha3_512(Sha3_512),
+    Shake128(Shake128),
+    Shake256(Shake256),
+    // Boxed: the subtree stack makes it far larger than the other states
+    Blake3(Box<Blake3>),
+}
+
+impl Hasher {
+    /// The algorithm this hasher computes.
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Feeds more data into the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        match &mut self.state {
+            State::Sha224(hasher) => hasher.update(data),
+            State::Sha256(hasher) => hasher.update(data),
+            State::Sha384(hasher) => hasher.update(data),
+            State::Sha512(hasher) => hasher.update(data),
+            State::Sha512_256(hasher) => hasher.update(data),
+            State::Sha3_224(hasher) => hasher.update(data),
+            State::Sha3_256(hasher) => hasher.update(data),
+            State::Sha3_384(hasher) => hasher.update(data),
+            State::Sha3_512(hasher) => hasher.update(data),
+            State::Shake128(hasher) => hasher.update(data),
+            State::Shake256(hasher) => hasher.update(data),
+            State::Blake3(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Returns the digest of all data passed to `update`, `output_len()` bytes long.
+    pub fn finalize(self) -> Vec<u8> {
+        match self.state {
+            State::Sha224(hasher) => hasher.finalize().to_vec(),
+            State::Sha256(hasher) => hasher.finalize().to_vec(),
+            State::Sha384(hasher) => hasher.finalize().to_vec(),
+            State::Sha512(hasher) => hasher.finalize().to_vec(),
+            State::Sha512_256(hasher) => hasher.finalize().to_vec(),
+            State::Sha3_224(hasher) => hasher.finalize().to_vec(),
+            State::Sha3_256(hasher) => hasher.finalize().to_vec(),
+            State::Sha3_384(hasher) => hasher.finalize().to_vec(),
+            State::Sha3_512(hasher) => hasher.finalize().to_vec(),
+            State::Shake128(hasher) => {
+                let mut output = alloc::vec![0u8; self.algorithm.output_len()];
+                hasher.finalize_xof().read(&mut output);
+                output
+            }
+            State::Shake256(hasher) => {
+                let mut output = alloc::vec![0u8; self.algorithm.output_len()];
+                hasher.finalize_xof().read(&mut output);
+                output
+            }
+            State::Blake3(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned when parsing the name of an unsupported algorithm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownAlgorithm(pub String);
+
+impl fmt::Display for UnknownAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown hash algorithm `{}`", self.0)
+    }
+}
+
+impl core::error::Error for UnknownAlgorithm {}
+
+impl FromStr for HashAlgorithm {
+    type Err = UnknownAlgorithm;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HashAlgorithm::ALL
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| UnknownAlgorithm(s.to_string()))
+    }
+}
+
+impl HashFunction for HashAlgorithm {
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha224 => crate::sha256::Sha224Hasher.hash(input),
+            HashAlgorithm::Sha256 => crate::sha256::Sha256Hasher.hash(input),
+            HashAlgorithm::Sha384 => crate::sha512::Sha384Hasher.hash(input),
+            HashAlgorithm::Sha512 => crate::sha512::Sha512Hasher.hash(input),
+            HashAlgorithm::Sha512_256 => crate::sha512::Sha512_256Hasher.hash(input),
+            HashAlgorithm::Sha3_224 => crate::sha3::Sha3_224Hasher.hash(input),
+            HashAlgorithm::Sha3_256 => crate::sha3::Sha3_256Hasher.hash(input),
+            HashAlgorithm::Sha3_384 => crate::sha3::Sha3_384Hasher.hash(input),
+            HashAlgorithm::Sha3_512 => crate::sha3::Sha3_512Hasher.hash(input),
+            HashAlgorithm::Shake128 => crate::sha3::Shake128Hasher::default().hash(input),
+            HashAlgorithm::Shake256 => crate::sha3::Shake256Hasher::default().hash(input),
+            HashAlgorithm::Blake3 => crate::blake3::Blake3Hasher.hash(input),
+        }
+    }
+
+    fn hash_many(&self, inputs: &[&[u8]]) -> Vec<Vec<u8>> {
+        match self {
+            HashAlgorithm::Sha256 => crate::sha256::Sha256Hasher.hash_many(inputs),
+            _ => inputs.iter().map(|input| self.hash(input)).collect(),
+        }
+    }
+
+    fn algorithm(&self) -> Option<HashAlgorithm> {
+        Some(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_algorithm_names_round_trip() {
+        for algorithm in HashAlgorithm::ALL {
+            assert_eq!(algorithm.name().parse::<HashAlgorithm>(), Ok(algorithm));
+            assert_eq!(algorithm.hash(b"abc").len(), algorithm.output_len());
+        }
+        assert_eq!("BLAKE3".parse::<HashAlgorithm>(), Ok(HashAlgorithm::Blake3));
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_algorithm_hash_reader_matches_hash() {
+        let input: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        for algorithm in HashAlgorithm::ALL {
+            let streamed = algorithm.hash_reader(input.as_slice()).unwrap();
+            assert_eq!(streamed, algorithm.hash(&input), "{}", algorithm);
+        }
+    }
+}
@@ -0,0 +1,573 @@
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+#[cfg(feature = "alloc")]
+use crate::utility::constant_time_eq;
+
+/// Size of a BLAKE3 chunk, the leaf of its internal Merkle tree
+pub const CHUNK_LEN: usize = 1024;
+
+const BLOCK_LEN: usize = 64;
+
+// Domain separation flags
+const CHUNK_START: u32 = 1 << 0;
+const CHUNK_END: u32 = 1 << 1;
+const PARENT: u32 = 1 << 2;
+const ROOT: u32 = 1 << 3;
+
+/// Initial chaining value, shared with SHA-256
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Message word permutation applied between rounds
+const MSG_PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+/// Computes the BLAKE3 hash of the input data.
+///
+/// # Arguments
+///
+/// * `input` - A byte slice containing the data to be hashed.
+///
+/// # Returns
+///
+/// A 32-byte array containing the BLAKE3 hash of the input data.
+pub fn blake3(input: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake3::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// BLAKE3 adapter for `merkle::HashFunction`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Blake3Hasher;
+
+#[cfg(feature = "alloc")]
+impl crate::merkle::HashFunction for Blake3Hasher {
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        blake3(input).to_vec()
+    }
+
+    fn algorithm(&self) -> Option<crate::algorithm::HashAlgorithm> {
+        Some(crate::algorithm::HashAlgorithm::Blake3)
+    }
+}
+
+/// The quarter-round mixing function.
+fn g(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, mx: u32, my: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(mx);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(my);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+}
+
+fn round(state: &mut [u32; 16], m: &[u32; 16]) {
+    // Mix the columns
+    g(state, 0, 4, 8, 12, m[0], m[1]);
+    g(state, 1, 5, 9, 13, m[2], m[3]);
+    g(state, 2, 6, 10, 14, m[4], m[5]);
+    g(state, 3, 7, 11, 15, m[6], m[7]);
+    // Mix the diagonals
+    g(state, 0, 5, 10, 15, m[8], m[9]);
+    g(state, 1, 6, 11, 12, m[10], m[11]);
+    g(state, 2, 7, 8, 13, m[12], m[13]);
+    g(state, 3, 4, 9, 14, m[14], m[15]);
+}
+
+fn permute(m: &mut [u32; 16]) {
+    let original = *m;
+    for (word, &source) in m.iter_mut().zip(MSG_PERMUTATION.iter()) {
+        *word = original[source];
+    }
+}
+
+/// Performs the BLAKE3 compression function on a single block.
+///
+/// # Arguments
+///
+/// * `chaining_value` - The chaining value of the chunk or the key.
+/// * `block_words` - The 64-byte block as little-endian words.
+/// * `counter` - The chunk index (or 0 for parent nodes and the first root output block).
+/// * `block_len` - Number of meaningful bytes in the block.
+/// * `flags` - Domain separation flags.
+fn compress(
+    chaining_value: &[u32; 8],
+    block_words: &[u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+) -> [u32; 16] {
+    let mut state = [
+        chaining_value[0], chaining_value[1], chaining_value[2], chaining_value[3],
+        chaining_value[4], chaining_value[5], chaining_value[6], chaining_value[7],
+        IV[0], IV[1], IV[2], IV[3],
+        counter as u32, (counter >> 32) as u32, block_len, flags,
+    ];
+    let mut block = *block_words;
+
+    // 7 rounds, permuting the message words in between
+    for i in 0..7 {
+        round(&mut state, &block);
+        if i < 6 {
+            permute(&mut block);
+        }
+    }
+
+    for i in 0..8 {
+        state[i] ^= state[i + 8];
+        state[i + 8] ^= chaining_value[i];
+    }
+    state
+}
+
+fn first_8_words(words: [u32; 16]) -> [u32; 8] {
+    let mut out = [0u32; 8];
+    out.copy_from_slice(&words[..8]);
+    out
+}
+
+fn words_from_le_bytes(bytes: &[u8], words: &mut [u32]) {
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+}
+
+fn cv_to_bytes(cv: &[u32; 8]) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(cv.iter()) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+/// The inputs to a final compression, kept around so the caller can decide
+/// whether it is an interior node or the root.
+struct Output {
+    input_chaining_value: [u32; 8],
+    block_words: [u32; 16],
+    counter: u64,
+    block_len: u32,
+    flags: u32,
+}
+
+impl Output {
+    fn chaining_value(&self) -> [u32; 8] {
+        first_8_words(compress(
+            &self.input_chaining_value,
+            &self.block_words,
+            self.counter,
+            self.block_len,
+            self.flags,
+        ))
+    }
+
+    fn root_hash(&self) -> [u8; 32] {
+        let words = compress(
+            &self.input_chaining_value,
+            &self.block_words,
+            0,
+            self.block_len,
+            self.flags | ROOT,
+        );
+        cv_to_bytes(&first_8_words(words))
+    }
+}
+
+/// Hashing state for a single 1024-byte chunk.
+#[derive(Clone)]
+struct ChunkState {
+    chaining_value: [u32; 8],
+    chunk_counter: u64,
+    block: [u8; BLOCK_LEN],
+    block_len: usize,
+    blocks_compressed: usize,
+}
+
+impl ChunkState {
+    fn new(chunk_counter: u64) -> Self {
+        ChunkState {
+            chaining_value: IV,
+            chunk_counter,
+            block: [0u8; BLOCK_LEN],
+            block_len: 0,
+            blocks_compressed: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        BLOCK_LEN * self.blocks_compressed + self.block_len
+    }
+
+    fn start_flag(&self) -> u32 {
+        if self.blocks_compressed == 0 {
+            CHUNK_START
+        } else {
+            0
+        }
+    }
+
+    fn update(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            // A full buffer is only compressed once more input arrives, since
+            // the last block of the chunk needs the CHUNK_END flag
+            if self.block_len == BLOCK_LEN {
+                let mut block_words = [0u32; 16];
+                words_from_le_bytes(&self.block, &mut block_words);
+                self.chaining_value = first_8_words(compress(
+                    &self.chaining_value,
+                    &block_words,
+                    self.chunk_counter,
+                    BLOCK_LEN as u32,
+                    self.start_flag(),
+                ));
+                self.blocks_compressed += 1;
+                self.block = [0u8; BLOCK_LEN];
+                self.block_len = 0;
+            }
+
+            let take = (BLOCK_LEN - self.block_len).min(input.len());
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&input[..take]);
+            self.block_len += take;
+            input = &input[take..];
+        }
+    }
+
+    fn output(&self) -> Output {
+        let mut block_words = [0u32; 16];
+        words_from_le_bytes(&self.block, &mut block_words);
+        Output {
+            input_chaining_value: self.chaining_value,
+            block_words,
+            counter: self.chunk_counter,
+            block_len: self.block_len as u32,
+            flags: self.start_flag() | CHUNK_END,
+        }
+    }
+}
+
+fn parent_output(left_child_cv: &[u32; 8], right_child_cv: &[u32; 8]) -> Output {
+    let mut block_words = [0u32; 16];
+    block_words[..8].copy_from_slice(left_child_cv);
+    block_words[8..].copy_from_slice(right_child_cv);
+    Output {
+        input_chaining_value: IV,
+        block_words,
+        counter: 0,
+        block_len: BLOCK_LEN as u32,
+        flags: PARENT,
+    }
+}
+
+fn parent_cv(left_child_cv: &[u32; 8], right_child_cv: &[u32; 8]) -> [u32; 8] {
+    parent_output(left_child_cv, right_child_cv).chaining_value()
+}
+
+/// Streaming BLAKE3 hasher.
+///
+/// Completed chunk chaining values are merged eagerly into a stack of
+/// subtree roots, so memory use is constant regardless of the input size.
+#[derive(Clone)]
+pub struct Blake3 {
+    chunk_state: ChunkState,
+    cv_stack: [[u32; 8]; 54], // Enough for 2^64 bytes of input
+    cv_stack_len: usize,
+}
+
+impl Blake3 {
+    /// Creates a hasher in the default (unkeyed) mode.
+    pub fn new() -> Self {
+        Blake3 {
+            chunk_state: ChunkState::new(0),
+            cv_stack: [[0u32; 8]; 54],
+            cv_stack_len: 0,
+        }
+    }
+
+    fn push_stack(&mut self, cv: [u32; 8]) {
+        self.cv_stack[self.cv_stack_len] = cv;
+        self.cv_stack_len += 1;
+    }
+
+    fn pop_stack(&mut self) -> [u32; 8] {
+        self.cv_stack_len -= 1;
+        self.cv_stack[self.cv_stack_len]
+    }
+
+    /// Merges a finished chunk into the stack; every trailing zero bit of the
+    /// chunk count is one completed subtree to fold into its parent.
+    fn add_chunk_chaining_value(&mut self, mut new_cv: [u32; 8], mut total_chunks: u64) {
+        while total_chunks & 1 == 0 {
+            new_cv = parent_cv(&self.pop_stack(), &new_cv);
+            total_chunks >>= 1;
+        }
+        self.push_stack(new_cv);
+    }
+
+    /// Feeds more data into the hasher.
+    pub fn update(&mut self, mut input: &[u8]) {
+        while !input.is_empty() {
+            // Only finish a full chunk once more input arrives, since the
+            // last chunk may be the root
+            if self.chunk_state.len() == CHUNK_LEN {
+                let chunk_cv = self.chunk_state.output().chaining_value();
+                let total_chunks = self.chunk_state.chunk_counter + 1;
+                self.add_chunk_chaining_value(chunk_cv, total_chunks);
+                self.chunk_state = ChunkState::new(total_chunks);
+            }
+
+            let take = (CHUNK_LEN - self.chunk_state.len()).min(input.len());
+            self.chunk_state.update(&input[..take]);
+            input = &input[take..];
+        }
+    }
+
+    /// Returns the 32-byte hash of all data passed to `update`.
+    pub fn finalize(self) -> [u8; 32] {
+        let mut output = self.chunk_state.output();
+        for cv in self.cv_stack[..self.cv_stack_len].iter().rev() {
+            output = parent_output(cv, &output.chaining_value());
+        }
+        output.root_hash()
+    }
+}
+
+impl Default for Blake3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number of chunks in the left subtree of a BLAKE3 node covering `count`
+/// chunks: the largest power of two strictly less than `count`.
+#[cfg(feature = "alloc")]
+fn left_subtree_len(count: usize) -> usize {
+    debug_assert!(count > 1);
+    1 << (usize::BITS - 1 - (count - 1).leading_zeros())
+}
+
+/// The chunk-level Merkle tree BLAKE3 builds internally.
+///
+/// Chunk `i` covers bytes `i * CHUNK_LEN..(i + 1) * CHUNK_LEN` of the input.
+/// The tree stores the chaining value of every chunk and every parent node
+/// and can prove that a single chunk belongs to the hashed input, against the
+/// ordinary BLAKE3 hash.
+#[cfg(feature = "alloc")]
+pub struct Blake3Tree {
+    /// Chaining values level by level, from the chunks up to the two children
+    /// of the root. The last node of an odd level is carried up unchanged,
+    /// which gives BLAKE3's left-full tree.
+    levels: Vec<Vec<[u32; 8]>>,
+    root: [u8; 32],
+}
+
+#[cfg(feature = "alloc")]
+impl Blake3Tree {
+    /// Hash the input, keeping the chaining value of every node
+    pub fn build(data: &[u8]) -> Self {
+        let mut chunk_cvs = Vec::new();
+        let mut output = ChunkState::new(0).output();
+        for (i, chunk) in data.chunks(CHUNK_LEN).enumerate() {
+            let mut state = ChunkState::new(i as u64);
+            state.update(chunk);
+            output = state.output();
+            chunk_cvs.push(output.chaining_value());
+        }
+
+        // A single chunk is the root itself
+        if chunk_cvs.len() <= 1 {
+            let levels = vec![vec![output.chaining_value()]];
+            return Blake3Tree { levels, root: output.root_hash() };
+        }
+
+        let mut levels = vec![chunk_cvs];
+        while let Some(level) = levels.last().filter(|level| level.len() > 2) {
+            let parents = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => parent_cv(left, right),
+                    _ => pair[0],
+                })
+                .collect();
+            levels.push(parents);
+        }
+        let top = &levels[levels.len() - 1];
+        let root = parent_output(&top[0], &top[1]).root_hash();
+
+        Blake3Tree { levels, root }
+    }
+
+    /// Get the BLAKE3 hash of the whole input
+    pub fn root_hash(&self) -> &[u8] {
+        &self.root
+    }
+
+    /// Get the number of chunks (at least one, even for empty input)
+    pub fn chunk_count(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Generate a proof for a given chunk index
+    ///
+    /// The proof lists the sibling chaining values from the chunk up to the
+    /// root; left/right positions follow from the index and the chunk count.
+    pub fn generate_proof(&self, chunk_index: usize) -> Option<Vec<Vec<u8>>> {
+        if chunk_index >= self.chunk_count() {
+            return None;
+        }
+
+        let mut proof = Vec::new();
+        let mut index = chunk_index;
+        for level in &self.levels {
+            // The last node of an odd level has no sibling
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(cv_to_bytes(sibling).to_vec());
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+
+    /// Verify a proof for a given chunk against an expected BLAKE3 hash
+    pub fn verify_proof(
+        chunk: &[u8],
+        chunk_index: usize,
+        chunk_count: usize,
+        proof: &[Vec<u8>],
+        expected_root: &[u8],
+    ) -> bool {
+        if chunk_index >= chunk_count || chunk.len() > CHUNK_LEN {
+            return false;
+        }
+
+        // Walk down from the root to find on which side each level sits
+        let mut is_right = Vec::new();
+        let mut size = chunk_count;
+        let mut index = chunk_index;
+        while size > 1 {
+            let split = left_subtree_len(size);
+            if index < split {
+                is_right.push(false);
+                size = split;
+            } else {
+                is_right.push(true);
+                index -= split;
+                size -= split;
+            }
+        }
+        if is_right.len() != proof.len() {
+            return false;
+        }
+
+        let mut state = ChunkState::new(chunk_index as u64);
+        state.update(chunk);
+        let mut output = state.output();
+
+        for (&right, sibling) in is_right.iter().rev().zip(proof.iter()) {
+            if sibling.len() != 32 {
+                return false;
+            }
+            let mut sibling_cv = [0u32; 8];
+            words_from_le_bytes(sibling, &mut sibling_cv);
+
+            let cv = output.chaining_value();
+            output = if right {
+                parent_output(&sibling_cv, &cv)
+            } else {
+                parent_output(&cv, &sibling_cv)
+            };
+        }
+
+        constant_time_eq(&output.root_hash(), expected_root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::utility::bytes_to_hex;
+    #[cfg(feature = "alloc")]
+    use alloc::vec::Vec;
+
+    #[cfg(feature = "alloc")]
+    fn test_input(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_blake3_known_values() {
+        let cases = [
+            (0, "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"),
+            (1, "2d3adedff11b61f14c886e35afa036736dcd87a74d27b5c1510225d0f592e213"),
+            (1024, "42214739f095a406f3fc83deb889744ac00df831c10daa55189b5d121c855af7"),
+            (1025, "d00278ae47eb27b34faecf67b4fe263f82d5412916c1ffd97c8cb7fb814b8444"),
+            (3072, "b98cb0ff3623be03326b373de6b9095218513e64f1ee2edd2525c7ad1e5cffd2"),
+            (8193, "bab6c09cb8ce8cf459261398d2e7aef35700bf488116ceb94a36d0f5f1b7bc3b"),
+        ];
+        for (len, expected_hex) in cases {
+            assert_eq!(bytes_to_hex(&blake3(&test_input(len))), expected_hex, "length {}", len);
+        }
+        assert_eq!(
+            bytes_to_hex(&blake3(b"abc")),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    #[test]
+    fn test_blake3_streaming_matches_one_shot() {
+        let input: [u8; 5000] = core::array::from_fn(|i| (i % 251) as u8);
+        let expected = blake3(&input);
+
+        for piece_len in [1, 63, 64, 1000, 1024, 1025] {
+            let mut hasher = Blake3::new();
+            for piece in input.chunks(piece_len) {
+                hasher.update(piece);
+            }
+            assert_eq!(hasher.finalize(), expected, "piece length {}", piece_len);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_blake3_tree_chunk_proofs() {
+        for len in [0, 100, 1024, 3000, 5000, 8193] {
+            let data = test_input(len);
+            let tree = Blake3Tree::build(&data);
+            assert_eq!(tree.root_hash(), blake3(&data));
+
+            let count = tree.chunk_count();
+            for index in 0..count {
+                let chunk = data.chunks(CHUNK_LEN).nth(index).unwrap_or(&[]);
+                let proof = tree.generate_proof(index).unwrap();
+                assert!(
+                    Blake3Tree::verify_proof(chunk, index, count, &proof, tree.root_hash()),
+                    "length {} chunk {}",
+                    len,
+                    index
+                );
+            }
+            assert!(tree.generate_proof(count).is_none());
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_blake3_tree_rejects_tampering() {
+        let data = test_input(5000);
+        let tree = Blake3Tree::build(&data);
+        let proof = tree.generate_proof(2).unwrap();
+        let chunk = &data[2 * CHUNK_LEN..3 * CHUNK_LEN];
+
+        let mut tampered = chunk.to_vec();
+        tampered[10] ^= 1;
+        assert!(!Blake3Tree::verify_proof(&tampered, 2, 5, &proof, tree.root_hash()));
+        // The chunk counter binds the position
+        assert!(!Blake3Tree::verify_proof(chunk, 3, 5, &proof, tree.root_hash()));
+    }
+}
@@ -0,0 +1,212 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::algorithm::HashAlgorithm;
+use crate::merkle::{HashFunction, MerkleTree};
+
+/// Chunk size used when none is given: 1 MiB.
+pub const DEFAULT_CHUNK_SIZE: u64 = 1 << 20;
+
+/// Tree hash of a file split into fixed-size chunks.
+///
+/// Every chunk is hashed on its own, so the chunks of a large file can be
+/// hashed on all cores at once. The chunk digests are the leaves of a
+/// `MerkleTree`, whose root identifies the file and gives an inclusion proof
+/// for each chunk. The root depends on the chunk size and the tree settings,
+/// which therefore have to be recorded next to it.
+///
+/// An empty file has a single, empty chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkedHash {
+    algorithm: HashAlgorithm,
+    chunk_size: u64,
+    chunk_digests: Vec<Vec<u8>>,
+}
+
+impl ChunkedHash {
+    /// Hashes a file, using one thread per available core.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file to hash.
+    /// * `algorithm` - The hash function for chunks and tree nodes.
+    /// * `chunk_size` - The size of every chunk but the last, in bytes.
+    pub fn hash_file(path: &Path, algorithm: HashAlgorithm, chunk_size: u64) -> io::Result<Self> {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::hash_file_with_threads(path, algorithm, chunk_size, threads)
+    }
+
+    /// Hashes a file using up to `threads` worker threads.
+    ///
+    /// Each worker opens the file itself and claims the next unhashed chunk
+    /// until none are left, streaming it through a fixed-size buffer.
+    pub fn hash_file_with_threads(
+        path: &Path,
+        algorithm: HashAlgorithm,
+        chunk_size: u64,
+        threads: usize,
+    ) -> io::Result<Self> {
+        check_chunk_size(chunk_size)?;
+
+        let len = File::open(path)?.metadata()?.len();
+        let chunk_count = len.div_ceil(chunk_size).max(1) as usize;
+        let threads = threads.clamp(1, chunk_count);
+        let next_chunk = AtomicUsize::new(0);
+
+        let results: Vec<io::Result<WorkerDigests>> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut file = File::open(path)?;
+                        let mut digests = Vec::new();
+                        loop {
+                            let index = next_chunk.fetch_add(1, Ordering::Relaxed);
+                            if index >= chunk_count {
+                                return Ok(digests);
+                            }
+                            file.seek(SeekFrom::Start(index as u64 * chunk_size))?;
+                            let digest = algorithm.hash_reader((&mut file).take(chunk_size))?;
+                            digests.push((index, digest));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("chunk hashing thread panicked"))
+                .collect()
+        });
+
+        let mut chunk_digests = vec![Vec::new(); chunk_count];
+        for result in results {
+            for (index, digest) in result? {
+                chunk_digests[index] = digest;
+            }
+        }
+
+        Ok(ChunkedHash { algorithm, chunk_size, chunk_digests })
+    }
+
+    /// Hashes everything read from `reader` on the current thread.
+    ///
+    /// Produces the same result as `hash_file`, for sources that cannot be
+    /// read at arbitrary offsets such as pipes.
+    pub fn hash_reader<R: Read>(
+        mut reader: R,
+        algorithm: HashAlgorithm,
+        chunk_size: u64,
+    ) -> io::Result<Self> {
+        check_chunk_size(chunk_size)?;
+
+        let mut chunk_digests = Vec::new();
+        loop {
+            let mut hasher = algorithm.hasher();
+            let read = io::copy(&mut (&mut reader).take(chunk_size), &mut hasher)?;
+            // A short final chunk ends the input; an empty one only counts for an empty input
+            if read > 0 || chunk_digests.is_empty() {
+                chunk_digests.push(hasher.finalize());
+            }
+            if read < chunk_size {
+                break;
+            }
+        }
+
+        Ok(ChunkedHash { algorithm, chunk_size, chunk_digests })
+    }
+
+    /// The algorithm used for chunks and tree nodes.
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// The size of every chunk but the last, in bytes.
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk_size
+    }
+
+    /// The digest of each chunk, in file order.
+    pub fn chunk_digests(&self) -> &[Vec<u8>] {
+        &self.chunk_digests
+    }
+
+    /// Builds `tree` over the chunk digests, e.g. for per-chunk proofs.
+    ///
+    /// The tree's hasher and settings decide how the digests are combined;
+    /// use the same ones whenever the root is recomputed.
+    pub fn build_tree<H: HashFunction>(&self, mut tree: MerkleTree<H>) -> MerkleTree<H> {
+        tree.build(self.chunk_digests.iter().map(Vec::as_slice).collect());
+        tree
+    }
+}
+
+/// The chunks one worker thread hashed, as `(chunk index, digest)`
+type WorkerDigests = Vec<(usize, Vec<u8>)>;
+
+fn check_chunk_size(chunk_size: u64) -> io::Result<()> {
+    if chunk_size == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "chunk size must not be zero"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_file(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("fs-guard-{}-{}", std::process::id(), name));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_chunked_parallel_matches_sequential() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let path = temp_file("chunked", &data);
+
+        for chunk_size in [1000, 1024, 4096, 10_000, 20_000] {
+            let expected =
+                ChunkedHash::hash_reader(data.as_slice(), HashAlgorithm::Sha256, chunk_size).unwrap();
+            let first_chunk = &data[..chunk_size.min(10_000) as usize];
+            assert_eq!(expected.chunk_digests().len(), 10_000usize.div_ceil(chunk_size as usize));
+            assert_eq!(expected.chunk_digests()[0], crate::sha256::sha256(first_chunk));
+
+            for threads in [1, 3, 8] {
+                let actual = ChunkedHash::hash_file_with_threads(
+                    &path,
+                    HashAlgorithm::Sha256,
+                    chunk_size,
+                    threads,
+                )
+                .unwrap();
+                assert_eq!(actual, expected, "chunk size {}, {} threads", chunk_size, threads);
+            }
+        }
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_chunked_proofs_and_empty_file() {
+        let data = vec![0x5a; 5000];
+        let chunked = ChunkedHash::hash_reader(data.as_slice(), HashAlgorithm::Blake3, 1024).unwrap();
+        let tree = chunked.build_tree(MerkleTree::new(HashAlgorithm::Blake3));
+        assert_eq!(tree.leaf_count(), 5);
+
+        let proof = tree.generate_proof(4).unwrap();
+        let root = tree.root_hash().unwrap();
+        assert!(tree.verify_proof(&chunked.chunk_digests()[4], 4, 5, proof, root));
+
+        let path = temp_file("chunked-empty", b"");
+        let empty = ChunkedHash::hash_file(&path, HashAlgorithm::Blake3, 1024).unwrap();
+        assert_eq!(empty, ChunkedHash::hash_reader(&b""[..], HashAlgorithm::Blake3, 1024).unwrap());
+        assert_eq!(empty.chunk_digests(), &[crate::blake3::blake3(b"").to_vec()]);
+        fs::remove_file(path).unwrap();
+
+        assert!(ChunkedHash::hash_reader(&b""[..], HashAlgorithm::Blake3, 0).is_err());
+    }
+}
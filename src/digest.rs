@@ -0,0 +1,344 @@
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
+
+#[cfg(feature = "alloc")]
+use crate::algorithm::HashAlgorithm;
+use crate::utility::constant_time_eq;
+
+/// Size of the largest digest any supported algorithm produces (SHA-512)
+pub const MAX_LEN: usize = 64;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// A hash value of up to `MAX_LEN` bytes.
+///
+/// Stored inline, so it needs no allocator. Equality is checked in constant
+/// time, which makes it safe to compare a computed root against an expected
+/// one. `Display` and `FromStr` use lowercase hex; base64, base32 and
+/// Subresource Integrity (`sha256-<base64>`) forms are available as well.
+#[derive(Clone, Copy)]
+pub struct Digest {
+    bytes: [u8; MAX_LEN],
+    len: usize,
+}
+
+/// Error returned when a digest cannot be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseDigestError {
+    /// A character outside the encoding's alphabet
+    InvalidCharacter,
+    /// Too long for a digest, or not a whole number of bytes
+    InvalidLength,
+    /// An SRI string without a known `<algorithm>-` prefix
+    UnknownAlgorithm,
+}
+
+impl fmt::Display for ParseDigestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseDigestError::InvalidCharacter => f.write_str("invalid character in digest"),
+            ParseDigestError::InvalidLength => f.write_str("invalid digest length"),
+            ParseDigestError::UnknownAlgorithm => f.write_str("unknown digest algorithm"),
+        }
+    }
+}
+
+impl core::error::Error for ParseDigestError {}
+
+impl Digest {
+    /// Creates a digest from raw bytes.
+    ///
+    /// # Returns
+    ///
+    /// The digest, or `None` if `bytes` is longer than `MAX_LEN`.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() > MAX_LEN {
+            return None;
+        }
+        let mut digest = Digest { bytes: [0u8; MAX_LEN], len: bytes.len() };
+        digest.bytes[..bytes.len()].copy_from_slice(bytes);
+        Some(digest)
+    }
+
+    /// Returns the digest bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// Returns the size of the digest in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` for a zero-length digest.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Parses padded standard base64.
+    pub fn from_base64(s: &str) -> Result<Self, ParseDigestError> {
+        decode_radix(s, 6, Some(4), |c| BASE64_ALPHABET.iter().position(|&a| a == c))
+    }
+
+    /// Parses padded RFC 4648 base32, in either case.
+    pub fn from_base32(s: &str) -> Result<Self, ParseDigestError> {
+        decode_radix(s, 5, Some(8), |c| {
+            BASE32_ALPHABET.iter().position(|&a| a == c.to_ascii_uppercase())
+        })
+    }
+
+    /// Encodes the digest as padded standard base64.
+    #[cfg(feature = "alloc")]
+    pub fn to_base64(&self) -> String {
+        encode_radix(self.as_bytes(), 6, BASE64_ALPHABET, 4)
+    }
+
+    /// Encodes the digest as padded RFC 4648 base32.
+    #[cfg(feature = "alloc")]
+    pub fn to_base32(&self) -> String {
+        encode_radix(self.as_bytes(), 5, BASE32_ALPHABET, 8)
+    }
+
+    /// Encodes the digest in Subresource Integrity form, `<algorithm>-<base64>`.
+    #[cfg(feature = "alloc")]
+    pub fn to_sri(&self, algorithm: HashAlgorithm) -> String {
+        alloc::format!("{}-{}", algorithm, self.to_base64())
+    }
+
+    /// Parses a Subresource Integrity string such as `sha256-<base64>`.
+    ///
+    /// # Returns
+    ///
+    /// The algorithm named by the prefix and the digest, whose length must
+    /// match that algorithm's output.
+    #[cfg(feature = "alloc")]
+    pub fn from_sri(s: &str) -> Result<(HashAlgorithm, Self), ParseDigestError> {
+        // Base64 has no '-', so the last one ends the algorithm name
+        let (name, encoded) = s.rsplit_once('-').ok_or(ParseDigestError::UnknownAlgorithm)?;
+        let algorithm: HashAlgorithm =
+            name.parse().map_err(|_| ParseDigestError::UnknownAlgorithm)?;
+        let digest = Self::from_base64(encoded)?;
+        if digest.len() != algorithm.output_len() {
+            return Err(ParseDigestError::InvalidLength);
+        }
+        Ok((algorithm, digest))
+    }
+}
+
+impl PartialEq for Digest {
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl Eq for Digest {}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.as_bytes() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Digest({})", self)
+    }
+}
+
+impl FromStr for Digest {
+    type Err = ParseDigestError;
+
+    /// Parses a hexadecimal digest, in either case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.len().is_multiple_of(2) || s.len() > 2 * MAX_LEN {
+            return Err(ParseDigestError::InvalidLength);
+        }
+        decode_radix(s, 4, None, |c| (c as char).to_digit(16).map(|v| v as usize))
+    }
+}
+
+/// Decodes a string where every character carries `bits` bits, most
+/// significant first. With `group`, the string must be padded with `=` to a
+/// multiple of that many characters, exactly as `encode_radix` does; without
+/// it, `=` is not allowed. Leftover bits must be zero, so each digest has
+/// exactly one encoding.
+fn decode_radix(
+    s: &str,
+    bits: u32,
+    group: Option<usize>,
+    value_of: impl Fn(u8) -> Option<usize>,
+) -> Result<Digest, ParseDigestError> {
+    let data = match group {
+        Some(group) => {
+            let data = s.trim_end_matches('=');
+            if s.len() != data.len().next_multiple_of(group) {
+                return Err(ParseDigestError::InvalidLength);
+            }
+            data
+        }
+        None => s,
+    };
+    if data.is_empty() {
+        return Err(ParseDigestError::InvalidLength);
+    }
+
+    let mut digest = Digest { bytes: [0u8; MAX_LEN], len: 0 };
+    let mut acc: u32 = 0;
+    let mut acc_bits = 0;
+
+    for &c in data.as_bytes() {
+        let value = value_of(c).ok_or(ParseDigestError::InvalidCharacter)?;
+        acc = (acc << bits) | value as u32;
+        acc_bits += bits;
+        if acc_bits >= 8 {
+            acc_bits -= 8;
+            if digest.len == MAX_LEN {
+                return Err(ParseDigestError::InvalidLength);
+            }
+            digest.bytes[digest.len] = (acc >> acc_bits) as u8;
+            digest.len += 1;
+            acc &= (1 << acc_bits) - 1;
+        }
+    }
+
+    if acc_bits >= bits || acc != 0 {
+        return Err(ParseDigestError::InvalidLength);
+    }
+    Ok(digest)
+}
+
+/// Encodes bytes `bits` at a time, padding with `=` to a multiple of `group` characters.
+#[cfg(feature = "alloc")]
+fn encode_radix(bytes: &[u8], bits: u32, alphabet: &[u8], group: usize) -> String {
+    let mut out = String::new();
+    let mut acc: u32 = 0;
+    let mut acc_bits = 0;
+
+    for &byte in bytes {
+        acc = (acc << 8) | byte as u32;
+        acc_bits += 8;
+        while acc_bits >= bits {
+            acc_bits -= bits;
+            out.push(alphabet[((acc >> acc_bits) & ((1 << bits) - 1)) as usize] as char);
+        }
+    }
+    if acc_bits > 0 {
+        out.push(alphabet[((acc << (bits - acc_bits)) & ((1 << bits) - 1)) as usize] as char);
+    }
+    while !out.len().is_multiple_of(group) {
+        out.push('=');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::sha256::{sha224, sha256};
+    #[cfg(feature = "alloc")]
+    use alloc::string::ToString;
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_digest_hex_round_trip() {
+        let digest = Digest::from_slice(&sha256(b"abc")).unwrap();
+        let hex = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+        assert_eq!(digest.to_string(), hex);
+        assert_eq!(hex.parse::<Digest>(), Ok(digest));
+        assert_eq!(hex.to_uppercase().parse::<Digest>(), Ok(digest));
+        assert_eq!("abc".parse::<Digest>(), Err(ParseDigestError::InvalidLength));
+        assert_eq!("zz".parse::<Digest>(), Err(ParseDigestError::InvalidCharacter));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_digest_base64_base32() {
+        let digest = Digest::from_slice(&sha256(b"abc")).unwrap();
+        assert_eq!(digest.to_base64(), "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=");
+        assert_eq!(digest.to_base32(), "XJ4BNP4PAHH6UQKBIDPF3LRCEOYAGYNDSYLXVHFUCD7WD4QACWWQ====");
+        assert_eq!(Digest::from_base64(&digest.to_base64()), Ok(digest));
+        assert_eq!(Digest::from_base32(&digest.to_base32().to_lowercase()), Ok(digest));
+
+        let short = Digest::from_slice(&sha224(b"abc")).unwrap();
+        assert_eq!(short.to_base64(), "Iwl9IjQF2CKGQqR3vaJVsyqtvOS9oLP342ydpw==");
+        assert_eq!(short.to_base32(), "EMEX2IRUAXMCFBSCUR333ISVWMVK3PHEXWQLH57DNSO2O===");
+        assert_eq!(Digest::from_base32(&short.to_base32()), Ok(short));
+
+        // Non-zero leftover bits are not a canonical encoding
+        assert!(Digest::from_base64("Iwl9IjQF2CKGQqR3vaJVsyqtvOS9oLP342ydpx==").is_err());
+    }
+
+    #[test]
+    fn test_digest_rejects_non_canonical_padding() {
+        use ParseDigestError::{InvalidCharacter, InvalidLength};
+
+        // Hex has no padding at all
+        assert_eq!("ab==".parse::<Digest>(), Err(InvalidCharacter));
+        assert_eq!("abcd=".parse::<Digest>(), Err(InvalidLength));
+        assert_eq!("".parse::<Digest>(), Err(InvalidLength));
+
+        // Base64 needs exactly the padding its length calls for
+        let base64 = "Iwl9IjQF2CKGQqR3vaJVsyqtvOS9oLP342ydpw";
+        assert!(Digest::from_base64(&[base64, "=="].concat()).is_ok());
+        for padding in ["", "=", "===", "===="] {
+            let encoded = [base64, padding].concat();
+            assert_eq!(Digest::from_base64(&encoded), Err(InvalidLength), "{}", encoded);
+        }
+        assert_eq!(
+            Digest::from_base64("ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=="),
+            Err(InvalidLength)
+        );
+        assert_eq!(Digest::from_base64(""), Err(InvalidLength));
+        assert_eq!(Digest::from_base64("===="), Err(InvalidLength));
+
+        // And so does base32
+        let base32 = "EMEX2IRUAXMCFBSCUR333ISVWMVK3PHEXWQLH57DNSO2O";
+        assert!(Digest::from_base32(&[base32, "==="].concat()).is_ok());
+        for padding in ["", "=", "==", "====", "========"] {
+            let encoded = [base32, padding].concat();
+            assert_eq!(Digest::from_base32(&encoded), Err(InvalidLength), "{}", encoded);
+        }
+        assert_eq!(Digest::from_base32(""), Err(InvalidLength));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_digest_sri() {
+        let digest = Digest::from_slice(&sha256(b"abc")).unwrap();
+        let sri = digest.to_sri(HashAlgorithm::Sha256);
+        assert_eq!(sri, "sha256-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=");
+        assert_eq!(Digest::from_sri(&sri), Ok((HashAlgorithm::Sha256, digest)));
+
+        assert_eq!(
+            Digest::from_sri("sha512-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0="),
+            Err(ParseDigestError::InvalidLength)
+        );
+        assert_eq!(
+            Digest::from_sri("md5-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0="),
+            Err(ParseDigestError::UnknownAlgorithm)
+        );
+    }
+
+    #[test]
+    fn test_digest_equality() {
+        let a = Digest::from_slice(&[1, 2, 3]).unwrap();
+        assert_eq!(a, Digest::from_slice(&[1, 2, 3]).unwrap());
+        assert_ne!(a, Digest::from_slice(&[1, 2, 4]).unwrap());
+        assert_ne!(a, Digest::from_slice(&[1, 2, 3, 0]).unwrap());
+        assert!(Digest::from_slice(&[0u8; MAX_LEN + 1]).is_none());
+    }
+}
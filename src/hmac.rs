@@ -0,0 +1,264 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::sha256::Sha256;
+use crate::utility::constant_time_eq;
+
+/// SHA-256 block size in bytes
+const BLOCK_LEN: usize = 64;
+
+/// Output size of HMAC-SHA256 in bytes
+const OUTPUT_LEN: usize = 32;
+
+/// Computes the HMAC-SHA256 of a message.
+///
+/// # Arguments
+///
+/// * `key` - The secret key. Keys longer than 64 bytes are hashed first.
+/// * `message` - The data to authenticate.
+///
+/// # Returns
+///
+/// A 32-byte authentication tag.
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new(key);
+    mac.update(message);
+    mac.finalize()
+}
+
+/// Streaming HMAC-SHA256.
+///
+/// The key is absorbed into the inner and outer SHA-256 states once, so a
+/// keyed instance can be cloned cheaply for every message.
+#[derive(Clone)]
+pub struct HmacSha256 {
+    inner: Sha256,
+    outer: Sha256,
+}
+
+impl HmacSha256 {
+    /// Creates an HMAC instance for the given key.
+    pub fn new(key: &[u8]) -> Self {
+        // Keys longer than a block are replaced by their hash
+        let mut block_key = [0u8; BLOCK_LEN];
+        if key.len() > BLOCK_LEN {
+            block_key[..OUTPUT_LEN].copy_from_slice(&crate::sha256::sha256(key));
+        } else {
+            block_key[..key.len()].copy_from_slice(key);
+        }
+
+        let mut ipad = [0x36u8; BLOCK_LEN];
+        let mut opad = [0x5cu8; BLOCK_LEN];
+        for ((i, o), k) in ipad.iter_mut().zip(opad.iter_mut()).zip(block_key.iter()) {
+            *i ^= k;
+            *o ^= k;
+        }
+
+        let mut inner = Sha256::new();
+        inner.update(&ipad);
+        let mut outer = Sha256::new();
+        outer.update(&opad);
+
+        HmacSha256 { inner, outer }
+    }
+
+    /// Feeds more of the message into the MAC.
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    /// Returns the authentication tag for all data passed to `update`.
+    pub fn finalize(self) -> [u8; 32] {
+        let inner_hash = self.inner.finalize();
+        let mut outer = self.outer;
+        outer.update(&inner_hash);
+        outer.finalize()
+    }
+
+    /// Checks the message against an expected tag in constant time.
+    pub fn verify(self, tag: &[u8]) -> bool {
+        constant_time_eq(&self.finalize(), tag)
+    }
+}
+
+/// Error returned when HKDF is asked for more than 255 blocks of output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidLength;
+
+impl fmt::Display for InvalidLength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HKDF output length exceeds {} bytes", 255 * OUTPUT_LEN)
+    }
+}
+
+impl core::error::Error for InvalidLength {}
+
+/// HKDF-Extract (RFC 5869) with HMAC-SHA256.
+///
+/// # Arguments
+///
+/// * `salt` - Optional non-secret random value; an empty slice means no salt.
+/// * `ikm` - The input keying material.
+///
+/// # Returns
+///
+/// The 32-byte pseudorandom key to pass to `hkdf_expand`.
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> [u8; 32] {
+    // An absent salt is a block of zeros, which HMAC pads to anyway
+    hmac_sha256(salt, ikm)
+}
+
+/// HKDF-Expand (RFC 5869) with HMAC-SHA256.
+///
+/// # Arguments
+///
+/// * `prk` - A pseudorandom key, usually the output of `hkdf_extract`.
+/// * `info` - Context that binds the derived key to its purpose, e.g. a manifest id.
+/// * `okm` - Buffer to fill with output keying material, at most 8160 bytes.
+pub fn hkdf_expand(prk: &[u8], info: &[u8], okm: &mut [u8]) -> Result<(), InvalidLength> {
+    if okm.len() > 255 * OUTPUT_LEN {
+        return Err(InvalidLength);
+    }
+
+    let keyed = HmacSha256::new(prk);
+    let mut previous: Option<[u8; 32]> = None;
+
+    for (i, chunk) in okm.chunks_mut(OUTPUT_LEN).enumerate() {
+        let mut mac = keyed.clone();
+        if let Some(previous) = &previous {
+            mac.update(previous);
+        }
+        mac.update(info);
+        mac.update(&[(i + 1) as u8]);
+        let block = mac.finalize();
+
+        chunk.copy_from_slice(&block[..chunk.len()]);
+        previous = Some(block);
+    }
+
+    Ok(())
+}
+
+/// Keyed `merkle::HashFunction` computing HMAC-SHA256 under a secret key.
+///
+/// A tree built with it can only be recomputed by someone holding the key,
+/// so rewriting both the files and the manifest does not yield a valid root.
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+pub struct KeyedHasher {
+    mac: HmacSha256,
+}
+
+#[cfg(feature = "alloc")]
+impl KeyedHasher {
+    /// Creates a hasher keyed with `key`.
+    pub fn new(key: &[u8]) -> Self {
+        KeyedHasher { mac: HmacSha256::new(key) }
+    }
+
+    /// Creates a hasher with a key derived through HKDF from a master key.
+    ///
+    /// # Arguments
+    ///
+    /// * `master_key` - The long-term secret.
+    /// * `salt` - Optional salt for the extract step.
+    /// * `info` - Context identifying what the key is for, e.g. a manifest id.
+    pub fn derive(master_key: &[u8], salt: &[u8], info: &[u8]) -> Self {
+        let prk = hkdf_extract(salt, master_key);
+        let mut key = [0u8; OUTPUT_LEN];
+        hkdf_expand(&prk, info, &mut key).expect("32 bytes is a valid HKDF length");
+        Self::new(&key)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl crate::merkle::HashFunction for KeyedHasher {
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        let mut mac = self.mac.clone();
+        mac.update(input);
+        mac.finalize().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::merkle::MerkleTree;
+    #[cfg(feature = "alloc")]
+    use crate::utility::bytes_to_hex;
+    #[cfg(feature = "alloc")]
+    use alloc::vec;
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_hmac_sha256_rfc4231() {
+        let tag = hmac_sha256(&[0x0b; 20], b"Hi There");
+        assert_eq!(
+            bytes_to_hex(&tag),
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"
+        );
+
+        let tag = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            bytes_to_hex(&tag),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        // Key longer than the block size
+        let tag = hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First");
+        assert_eq!(
+            bytes_to_hex(&tag),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_hmac_sha256_streaming_verify() {
+        let mut mac = HmacSha256::new(b"Jefe");
+        mac.update(b"what do ya want ");
+        mac.update(b"for nothing?");
+        assert!(mac.clone().verify(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")));
+        assert!(!mac.verify(&[0u8; 32]));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_hkdf_rfc5869() {
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+
+        let prk = hkdf_extract(&salt, &[0x0b; 22]);
+        assert_eq!(
+            bytes_to_hex(&prk),
+            "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5"
+        );
+
+        let mut okm = [0u8; 42];
+        hkdf_expand(&prk, &info, &mut okm).unwrap();
+        assert_eq!(
+            bytes_to_hex(&okm),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+
+        let mut too_long = vec![0u8; 255 * 32 + 1];
+        assert_eq!(hkdf_expand(&prk, &info, &mut too_long), Err(InvalidLength));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_keyed_merkle_tree() {
+        let data_blocks: Vec<&[u8]> = vec![b"block1", b"block2", b"block3"];
+
+        let mut tree_a = MerkleTree::new(KeyedHasher::derive(b"master", b"", b"manifest-a"));
+        tree_a.build(data_blocks.clone());
+        let mut tree_b = MerkleTree::new(KeyedHasher::derive(b"master", b"", b"manifest-b"));
+        tree_b.build(data_blocks.clone());
+
+        assert_ne!(tree_a.root_hash(), tree_b.root_hash());
+
+        let proof = tree_a.generate_proof(1).unwrap();
+        assert!(tree_a.verify_proof(b"block2", 1, 3, proof, tree_a.root_hash().unwrap()));
+    }
+}
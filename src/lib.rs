@@ -1,2 +1,24 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+pub mod algorithm;
+pub mod blake3;
+#[cfg(feature = "std")]
+pub mod chunked;
+pub mod digest;
+pub mod hmac;
+#[cfg(feature = "alloc")]
+pub mod merkle;
+#[cfg(feature = "alloc")]
+pub mod mmr;
+#[cfg(feature = "alloc")]
+pub mod proof;
 pub mod sha256;
+pub mod sha3;
+pub mod sha512;
+#[cfg(feature = "alloc")]
+pub mod sparse;
 pub mod utility;
@@ -1,121 +1,258 @@
-mod sha256;
-mod merkle;
-mod utility;
+mod manifest;
 
-use crate::utility::bytes_to_hex;
-struct Sha256Hasher;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
 
-impl crate::merkle::HashFunction for Sha256Hasher {
-    fn hash(&self, input: &[u8]) -> Vec<u8> {
-        crate::sha256::sha256(input).to_vec()
-    }
-}
+use fs_guard::algorithm::HashAlgorithm;
+use fs_guard::chunked::{ChunkedHash, DEFAULT_CHUNK_SIZE};
+use fs_guard::digest::Digest;
 
-/*fn main() -> io::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <file_or_directory>", args[0]);
-        return Ok(());
-    }
+use crate::manifest::{Entry, Manifest, TreeScheme};
 
-    let path = &args[1];
-    let metadata = fs::metadata(path)?;
+const USAGE: &str = "\
+Usage:
+  fs-guard build [--algorithm <name>] [--tree <rfc6962|legacy>]
+                 [--chunked] [--chunk-size <bytes>] <file_or_directory>
+  fs-guard verify [--root <hex|sri>] <manifest> <file_or_directory>";
 
-    let mut data_blocks = Vec::new();
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    if metadata.is_file() {
-        // Read the file content
-        let mut file = fs::File::open(path)?;
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?;
-        data_blocks.push(buffer);
-    } else if metadata.is_dir() {
-        // Read each file in the directory
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                let mut file = fs::File::open(path)?;
-                let mut buffer = Vec::new();
-                file.read_to_end(&mut buffer)?;
-                data_blocks.push(buffer);
-            }
+    let result = match args.first().map(String::as_str) {
+        Some("build") => build(&args[1..]).map(|()| true),
+        Some("verify") => verify(&args[1..]),
+        _ => Err(usage_error()),
+    };
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(err) => {
+            eprintln!("fs-guard: {}", err);
+            process::exit(2);
         }
-    } else {
-        eprintln!("Invalid path: {}", path);
-        return Ok(());
     }
+}
 
-    // Create a new Merkle Tree with the SHA-256 hasher
-    let mut merkle_tree = crate::merkle::MerkleTree::new(Sha256Hasher);
-
-    // Convert data_blocks to slices
-    let data_slices: Vec<&[u8]> = data_blocks.iter().map(|block| block.as_slice()).collect();
+fn usage_error() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("invalid arguments\n{}", USAGE))
+}
 
-    // Build the Merkle Tree
-    merkle_tree.build(data_slices);
+/// Hash every file under `path` and print a manifest to stdout
+///
+/// New manifests use RFC 6962 domain separation unless `--tree legacy` asks
+/// for the old scheme. With `--chunked`, each file is hashed as a tree of
+/// fixed-size chunks spread across all cores, which pays off for very large
+/// files. The chunks are `DEFAULT_CHUNK_SIZE` bytes unless `--chunk-size`
+/// (which implies `--chunked`) says otherwise.
+fn build(args: &[String]) -> io::Result<()> {
+    let mut algorithm = HashAlgorithm::Sha256;
+    let mut tree = TreeScheme::Rfc6962;
+    let mut chunked = false;
+    let mut chunk_size = None;
+    let mut path = None;
 
-    // Get the Merkle root
-    if let Some(root_hash) = merkle_tree.root_hash() {
-        println!("Merkle Root: {:?}", bytes_to_hex(root_hash));
-    } else {
-        println!("Merkle Tree is empty.");
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--algorithm" => {
+                let name = args.next().ok_or_else(usage_error)?;
+                algorithm = name
+                    .parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            }
+            "--tree" => {
+                let name = args.next().ok_or_else(usage_error)?;
+                tree = name
+                    .parse()
+                    .map_err(|e: String| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            }
+            "--chunked" => chunked = true,
+            "--chunk-size" => {
+                let size = args.next().ok_or_else(usage_error)?;
+                chunk_size = Some(size.parse::<u64>().ok().filter(|&size| size > 0).ok_or_else(
+                    || io::Error::new(io::ErrorKind::InvalidInput, "invalid chunk size"),
+                )?);
+            }
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => return Err(usage_error()),
+        }
     }
+    let path = path.ok_or_else(usage_error)?;
+    let chunk_size = chunk_size.or(chunked.then_some(DEFAULT_CHUNK_SIZE));
+
+    let entries = hash_files(&path, algorithm, tree, chunk_size)?;
+    let root = merkle_root(algorithm, tree, &entries);
 
+    print!("{}", Manifest { algorithm, tree, chunk_size, root, entries });
     Ok(())
-}*/
+}
 
-fn main() {
-    // Create a new Merkle Tree with the SHA-256 hasher
-    let mut merkle_tree = merkle::MerkleTree::new(Sha256Hasher);
+/// Check the files under `path` against a manifest, reporting every difference
+///
+/// With `--root`, the manifest itself is only trusted if its root matches the
+/// given value, so a manifest rewritten together with the files is caught.
+fn verify(args: &[String]) -> io::Result<bool> {
+    let mut trusted_root = None;
+    let mut positional = Vec::new();
 
-    // Data blocks to be included in the Merkle Tree
-    let data_blocks: Vec<&[u8]> = vec![b"block1", b"block2", b"block3", b"block4"];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" => trusted_root = Some(args.next().ok_or_else(usage_error)?),
+            _ => positional.push(arg),
+        }
+    }
+    let [manifest_path, path] = positional[..] else {
+        return Err(usage_error());
+    };
 
-    // Build the Merkle Tree
-    merkle_tree.build(data_blocks);
+    let text = fs::read_to_string(manifest_path)?;
+    let manifest = Manifest::parse(&text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
-    // Get the Merkle root
-    if let Some(root_hash) = merkle_tree.root_hash() {
-        #[cfg(debug_assertions)]
-        println!("Merkle Root: {:?}", bytes_to_hex(root_hash));
+    if let Some(trusted_root) = trusted_root {
+        let trusted_root = parse_root(trusted_root, manifest.algorithm)?;
+        if manifest.root != Some(trusted_root) {
+            println!("ROOT MISMATCH");
+            println!("FAILED");
+            return Ok(false);
+        }
+    }
 
-        // Generate a proof for the first leaf
-        if let Some(proof) = merkle_tree.generate_proof(0) {
-            println!("Proof for first leaf: {:?}", proof.iter().map(|h| bytes_to_hex(h)).collect::<Vec<_>>());
+    let entries = hash_files(
+        Path::new(path),
+        manifest.algorithm,
+        manifest.tree,
+        manifest.chunk_size,
+    )?;
+    let mut ok = true;
 
-            // Verify the proof
-            let is_valid = merkle_tree.verify_proof(b"block1", proof, root_hash);
-            println!("Proof is valid: {}", is_valid);
+    for expected in &manifest.entries {
+        match entries.iter().find(|entry| entry.path == expected.path) {
+            None => {
+                println!("MISSING  {}", expected.path);
+                ok = false;
+            }
+            Some(actual) if actual.digest != expected.digest => {
+                println!("CHANGED  {}", expected.path);
+                ok = false;
+            }
+            Some(_) => {}
         }
-        
-        // Generate a proof for the second leaf
-        if let Some(proof) = merkle_tree.generate_proof(1) {
-            println!("Proof for second leaf: {:?}", proof.iter().map(|h| bytes_to_hex(h)).collect::<Vec<_>>());
-
-            // Verify the proof
-            let is_valid = merkle_tree.verify_proof(b"block2", proof, root_hash);
-            println!("Proof is valid: {}", is_valid);
+    }
+    for actual in &entries {
+        if !manifest.entries.iter().any(|entry| entry.path == actual.path) {
+            println!("NEW      {}", actual.path);
+            ok = false;
         }
-        
-        // Generate a proof for the third leaf
-        if let Some(proof) = merkle_tree.generate_proof(2) {
-            println!("Proof for third leaf: {:?}", proof.iter().map(|h| bytes_to_hex(h)).collect::<Vec<_>>());
-
-            // Verify the proof
-            let is_valid = merkle_tree.verify_proof(b"block3", proof, root_hash);
-            println!("Proof is valid: {}", is_valid);
+    }
+
+    // The root covers the order and the set of files as well
+    if merkle_root(manifest.algorithm, manifest.tree, &entries) != manifest.root {
+        // Any difference above changes the root too, so only name it on its own
+        if ok {
+            println!("ROOT MISMATCH");
         }
-        
-        // Generate a proof for the first leaf
-        if let Some(proof) = merkle_tree.generate_proof(3) {
-            println!("Proof for fourth leaf: {:?}", proof.iter().map(|h| bytes_to_hex(h)).collect::<Vec<_>>());
-
-            // Verify the proof
-            let is_valid = merkle_tree.verify_proof(b"block4", proof, root_hash);
-            println!("Proof is valid: {}", is_valid);
+        ok = false;
+    }
+
+    println!("{}", if ok { "OK" } else { "FAILED" });
+    Ok(ok)
+}
+
+/// Parse a root given as hex or as an SRI string naming the manifest's algorithm
+fn parse_root(value: &str, algorithm: HashAlgorithm) -> io::Result<Digest> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+
+    let root = if value.contains('-') {
+        let (sri_algorithm, root) =
+            Digest::from_sri(value).map_err(|e| invalid(format!("invalid root: {}", e)))?;
+        if sri_algorithm != algorithm {
+            return Err(invalid(format!(
+                "root uses {} but the manifest uses {}",
+                sri_algorithm, algorithm
+            )));
         }
+        root
+    } else {
+        value.parse::<Digest>().map_err(|e| invalid(format!("invalid root: {}", e)))?
+    };
+
+    if root.len() != algorithm.output_len() {
+        return Err(invalid("invalid root: wrong length for the manifest's algorithm".to_string()));
+    }
+    Ok(root)
+}
+
+/// Hash each file, yielding entries sorted by relative path
+fn hash_files(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    tree: TreeScheme,
+    chunk_size: Option<u64>,
+) -> io::Result<Vec<Entry>> {
+    let metadata = fs::metadata(path)?;
+
+    let mut files = Vec::new();
+    if metadata.is_file() {
+        let name = path.file_name().map(PathBuf::from).unwrap_or_else(|| path.to_path_buf());
+        files.push((path.to_path_buf(), name));
+    } else if metadata.is_dir() {
+        collect_files(path, path, &mut files)?;
     } else {
-        println!("Merkle Tree is empty.");
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid path: {}", path.display()),
+        ));
     }
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+
+    files
+        .into_iter()
+        .map(|(full, relative)| {
+            let digest = match chunk_size {
+                Some(chunk_size) => ChunkedHash::hash_file(&full, algorithm, chunk_size)?
+                    .build_tree(tree.merkle_tree(algorithm))
+                    .root()
+                    .expect("a chunked hash has at least one chunk"),
+                None => Digest::from_slice(&algorithm.hash_reader(fs::File::open(&full)?)?)
+                    .expect("algorithm output fits in a digest"),
+            };
+            Ok(Entry {
+                digest,
+                path: relative.to_string_lossy().into_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Recursively collect `(full path, path relative to root)` for every file
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<(PathBuf, PathBuf)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            files.push((path, relative));
+        }
+    }
+    Ok(())
+}
+
+/// Merkle root over the file digests, in manifest order
+fn merkle_root(algorithm: HashAlgorithm, tree: TreeScheme, entries: &[Entry]) -> Option<Digest> {
+    let mut merkle_tree = tree.merkle_tree(algorithm);
+    let leaves = entries.iter().map(|entry| entry.digest.as_bytes()).collect();
+    #[cfg(feature = "parallel")]
+    merkle_tree.build_parallel(leaves);
+    #[cfg(not(feature = "parallel"))]
+    merkle_tree.build(leaves);
+    merkle_tree.root()
 }
@@ -0,0 +1,222 @@
+use std::fmt;
+use std::str::FromStr;
+
+use fs_guard::algorithm::HashAlgorithm;
+use fs_guard::digest::Digest;
+use fs_guard::merkle::{MerkleTree, OddNodePolicy, PairOrder, TreeMode};
+
+/// First line of every manifest
+const HEADER: &str = "fs-guard manifest v1";
+
+/// A single file recorded in a manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub digest: Digest,
+    pub path: String,
+}
+
+/// The digests of a set of files and the Merkle root over them.
+///
+/// The text format is a header block of `key: value` lines, a blank line,
+/// then one `<hex digest>  <path>` line per file:
+///
+/// ```text
+/// fs-guard manifest v1
+/// algorithm: sha256
+/// root: 5f2c...
+///
+/// 9a0b...  etc/hosts
+/// ```
+///
+/// The `tree` line names the `TreeScheme` of the Merkle trees; manifests
+/// written before schemes existed have no `tree` line and use `legacy`.
+///
+/// With a `chunk-size: <bytes>` line, each file digest is the root of a
+/// Merkle tree over the file's chunks rather than a hash of the whole file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub algorithm: HashAlgorithm,
+    pub tree: TreeScheme,
+    pub chunk_size: Option<u64>,
+    pub root: Option<Digest>,
+    pub entries: Vec<Entry>,
+}
+
+impl Manifest {
+    /// Parse a manifest from its text form
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate();
+
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            _ => return Err(format!("missing `{}` header", HEADER)),
+        }
+
+        let mut algorithm = None;
+        let mut tree = TreeScheme::Legacy;
+        let mut chunk_size = None;
+        let mut root = None;
+
+        for (number, line) in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line
+                .split_once(": ")
+                .ok_or_else(|| format!("line {}: expected `key: value`", number + 1))?;
+            match key {
+                "algorithm" => {
+                    algorithm = Some(value.parse::<HashAlgorithm>().map_err(|e| e.to_string())?)
+                }
+                "tree" => {
+                    tree = value.parse().map_err(|e| format!("line {}: {}", number + 1, e))?
+                }
+                "chunk-size" => {
+                    chunk_size = Some(
+                        value
+                            .parse::<u64>()
+                            .ok()
+                            .filter(|&size| size > 0)
+                            .ok_or_else(|| format!("line {}: invalid chunk size", number + 1))?,
+                    )
+                }
+                "root" => {
+                    root = Some(
+                        value
+                            .parse::<Digest>()
+                            .map_err(|e| format!("line {}: {}", number + 1, e))?,
+                    )
+                }
+                _ => return Err(format!("line {}: unknown key `{}`", number + 1, key)),
+            }
+        }
+
+        let algorithm = algorithm.ok_or("missing `algorithm`")?;
+
+        let mut entries = Vec::new();
+        for (number, line) in lines {
+            let (digest, path) = line
+                .split_once("  ")
+                .ok_or_else(|| format!("line {}: expected `<digest>  <path>`", number + 1))?;
+            let digest = digest
+                .parse::<Digest>()
+                .ok()
+                .filter(|digest| digest.len() == algorithm.output_len())
+                .ok_or_else(|| format!("line {}: invalid digest", number + 1))?;
+            entries.push(Entry { digest, path: path.to_string() });
+        }
+
+        Ok(Manifest { algorithm, tree, chunk_size, root, entries })
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "algorithm: {}", self.algorithm)?;
+        writeln!(f, "tree: {}", self.tree)?;
+        if let Some(chunk_size) = self.chunk_size {
+            writeln!(f, "chunk-size: {}", chunk_size)?;
+        }
+        if let Some(root) = &self.root {
+            writeln!(f, "root: {}", root)?;
+        }
+        writeln!(f)?;
+        for entry in &self.entries {
+            writeln!(f, "{}  {}", entry.digest, entry.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the Merkle trees of a manifest hash and combine nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeScheme {
+    /// Unprefixed hashes of sorted pairs, duplicating odd nodes, as written
+    /// by the first releases
+    Legacy,
+    /// The RFC 6962 tree: prefixed hashes, children in position order, and
+    /// odd-sized levels split rather than padded
+    Rfc6962,
+}
+
+impl TreeScheme {
+    const ALL: [TreeScheme; 2] = [TreeScheme::Legacy, TreeScheme::Rfc6962];
+
+    /// The name of the scheme in manifests and on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            TreeScheme::Legacy => "legacy",
+            TreeScheme::Rfc6962 => "rfc6962",
+        }
+    }
+
+    /// An empty Merkle tree configured for this scheme
+    pub fn merkle_tree(&self, algorithm: HashAlgorithm) -> MerkleTree<HashAlgorithm> {
+        match self {
+            TreeScheme::Legacy => MerkleTree::new(algorithm)
+                .with_mode(TreeMode::Legacy)
+                .with_pair_order(PairOrder::Sorted)
+                .with_odd_node_policy(OddNodePolicy::Duplicate),
+            TreeScheme::Rfc6962 => MerkleTree::new(algorithm)
+                .with_mode(TreeMode::Rfc6962)
+                .with_pair_order(PairOrder::Positional)
+                .with_odd_node_policy(OddNodePolicy::RFC6962_SPLIT),
+        }
+    }
+}
+
+impl fmt::Display for TreeScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TreeScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TreeScheme::ALL
+            .into_iter()
+            .find(|scheme| scheme.name() == s)
+            .ok_or_else(|| format!("unknown tree scheme `{}`", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(byte: u8) -> Digest {
+        Digest::from_slice(&[byte; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let manifest = Manifest {
+            algorithm: HashAlgorithm::Blake3,
+            tree: TreeScheme::Rfc6962,
+            chunk_size: Some(1 << 20),
+            root: Digest::from_slice(&[0xab; 32]),
+            entries: vec![
+                Entry { digest: digest(0x01), path: "a.txt".to_string() },
+                Entry { digest: digest(0x02), path: "dir/b c.txt".to_string() },
+            ],
+        };
+
+        let text = manifest.to_string();
+        assert_eq!(Manifest::parse(&text), Ok(manifest));
+    }
+
+    #[test]
+    fn test_manifest_without_tree_is_legacy() {
+        let text = format!("{}\nalgorithm: sha256\n\n", HEADER);
+        assert_eq!(Manifest::parse(&text).unwrap().tree, TreeScheme::Legacy);
+    }
+
+    #[test]
+    fn test_manifest_rejects_bad_digest() {
+        let text = format!("{}\nalgorithm: sha256\n\nabcd  a.txt\n", HEADER);
+        assert!(Manifest::parse(&text).is_err());
+    }
+}
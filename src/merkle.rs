@@ -1,209 +1,753 @@
-use std::fmt::Debug;
-#[cfg(debug_assertions)]
-use crate::utility;
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use crate::algorithm::HashAlgorithm;
+use crate::digest::Digest;
+use crate::utility::constant_time_eq;
 
 /// Trait for hashing functions
 pub trait HashFunction {
     fn hash(&self, input: &[u8]) -> Vec<u8>;
+
+    /// Hash a batch of independent inputs
+    ///
+    /// The default implementation hashes each input in turn. Hashers with a
+    /// multi-buffer backend can override it; the tree uses it for every level.
+    fn hash_many(&self, inputs: &[&[u8]]) -> Vec<Vec<u8>> {
+        inputs.iter().map(|input| self.hash(input)).collect()
+    }
+
+    /// The algorithm this hasher computes, if it is one of `HashAlgorithm`
+    ///
+    /// Keyed and custom hashers keep the default, `None`.
+    fn algorithm(&self) -> Option<HashAlgorithm> {
+        None
+    }
+}
+
+impl<H: HashFunction + ?Sized> HashFunction for &H {
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        (**self).hash(input)
+    }
+
+    fn hash_many(&self, inputs: &[&[u8]]) -> Vec<Vec<u8>> {
+        (**self).hash_many(inputs)
+    }
+
+    fn algorithm(&self) -> Option<HashAlgorithm> {
+        (**self).algorithm()
+    }
+}
+
+/// Prefix for leaf hashes in `TreeMode::Rfc6962`
+pub const LEAF_PREFIX: u8 = 0x00;
+/// Prefix for interior node hashes in `TreeMode::Rfc6962`
+pub const NODE_PREFIX: u8 = 0x01;
+
+/// How leaves and interior nodes are hashed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TreeMode {
+    /// Leaves are `H(data)` and nodes `H(left || right)`.
+    ///
+    /// A 64-byte leaf can then be passed off as an interior node, which
+    /// allows second-preimage attacks. Only kept to check existing roots.
+    #[default]
+    Legacy,
+    /// Leaves are `H(0x00 || data)` and nodes `H(0x01 || left || right)`, as in RFC 6962
+    Rfc6962,
+}
+
+/// How the two children of a node are ordered before hashing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PairOrder {
+    /// Left child first, so the root commits to the position of every leaf
+    #[default]
+    Positional,
+    /// Smaller hash first, as in OpenZeppelin's `MerkleProof`.
+    ///
+    /// Proofs need no directions, but only show that a leaf is somewhere in
+    /// the tree, not where.
+    Sorted,
+}
+
+/// What to do with the last node of a level that has an odd number of nodes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OddNodePolicy {
+    /// Pair the node with a copy of itself.
+    ///
+    /// As in Bitcoin (CVE-2012-2459), a list whose last element is repeated
+    /// then has the same root as the list without the repeat.
+    #[default]
+    Duplicate,
+    /// Carry the node up to the next level unchanged.
+    ///
+    /// This is the tree RFC 6962 describes top-down, splitting `n` leaves
+    /// into the largest power of two below `n` and the rest, recursively.
+    #[doc(alias = "rfc6962")]
+    Promote,
+}
+
+impl OddNodePolicy {
+    /// The RFC 6962 split, which builds the same tree as `Promote`
+    pub const RFC6962_SPLIT: OddNodePolicy = OddNodePolicy::Promote;
 }
 
-/// Merkle Tree Node
-#[derive(Debug, Clone)]
-pub struct MerkleNode {
-    hash: Vec<u8>,
-    #[allow(dead_code)]
-    left: Option<Box<MerkleNode>>,
-    #[allow(dead_code)]
-    right: Option<Box<MerkleNode>>,
+/// Proof that several leaves are part of a tree
+///
+/// Sibling hashes shared by the paths of several leaves, and siblings that
+/// are themselves among the proven leaves, are left out, so the proof is much
+/// smaller than one `generate_proof` per leaf.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiProof {
+    /// Positions of the proven leaves, strictly ascending
+    pub indices: Vec<usize>,
+    /// Number of leaves in the tree
+    pub leaf_count: usize,
+    /// Sibling hashes level by level from the leaves up, left to right within a level
+    pub hashes: Vec<Vec<u8>>,
 }
 
 /// Merkle Tree
+///
+/// Every level is stored as one contiguous buffer of equally sized hashes,
+/// from the leaf hashes up to the root, so a tree over `n` leaves takes
+/// `O(n)` memory and a proof is a lookup of one sibling per level.
 pub struct MerkleTree<H: HashFunction> {
-    root: Option<MerkleNode>,
     hasher: H,
-    leaves: Vec<MerkleNode>,
+    mode: TreeMode,
+    pair_order: PairOrder,
+    odd_node_policy: OddNodePolicy,
+    digest_len: usize,
+    levels: Vec<Vec<u8>>,
 }
 
 impl<H: HashFunction> MerkleTree<H> {
     /// Create a new Merkle Tree with the given hasher
     pub fn new(hasher: H) -> Self {
-        MerkleTree { root: None, hasher, leaves: Vec::new() }
+        MerkleTree {
+            hasher,
+            mode: TreeMode::Legacy,
+            pair_order: PairOrder::Positional,
+            odd_node_policy: OddNodePolicy::Duplicate,
+            digest_len: 0,
+            levels: Vec::new(),
+        }
+    }
+
+    /// Use `mode` to hash leaves and nodes; call before `build`
+    pub fn with_mode(mut self, mode: TreeMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The way this tree hashes leaves and nodes
+    pub fn mode(&self) -> TreeMode {
+        self.mode
+    }
+
+    /// Order each pair of children by `pair_order`; call before `build`
+    pub fn with_pair_order(mut self, pair_order: PairOrder) -> Self {
+        self.pair_order = pair_order;
+        self
+    }
+
+    /// The way this tree orders each pair of children
+    pub fn pair_order(&self) -> PairOrder {
+        self.pair_order
+    }
+
+    /// Handle odd-sized levels by `policy`; call before `build`
+    pub fn with_odd_node_policy(mut self, policy: OddNodePolicy) -> Self {
+        self.odd_node_policy = policy;
+        self
+    }
+
+    /// The way this tree handles odd-sized levels
+    pub fn odd_node_policy(&self) -> OddNodePolicy {
+        self.odd_node_policy
+    }
+
+    /// The hasher for leaves and nodes
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    /// Whether the last node of a level of `level_len` nodes is carried up unpaired
+    fn promotes_last(&self, level_len: usize) -> bool {
+        level_len % 2 == 1 && self.odd_node_policy != OddNodePolicy::Duplicate
+    }
+
+    /// The number of leaves the tree was built from
+    pub fn leaf_count(&self) -> usize {
+        self.level_len(0)
+    }
+
+    /// The number of nodes in a level, 0 for levels the tree does not have
+    fn level_len(&self, level: usize) -> usize {
+        self.levels.get(level).map_or(0, |nodes| nodes.len() / self.digest_len)
+    }
+
+    /// The hash of node `index` in `level`
+    fn node(&self, level: usize, index: usize) -> &[u8] {
+        &self.levels[level][index * self.digest_len..(index + 1) * self.digest_len]
+    }
+
+    /// Hash a batch of leaves, adding the leaf prefix if the mode calls for it
+    fn hash_leaves(&self, data_blocks: &[&[u8]]) -> Vec<Vec<u8>> {
+        match self.mode {
+            TreeMode::Legacy => self.hasher.hash_many(data_blocks),
+            TreeMode::Rfc6962 => {
+                let prefixed: Vec<Vec<u8>> =
+                    data_blocks.iter().map(|data| [&[LEAF_PREFIX], *data].concat()).collect();
+                let inputs: Vec<&[u8]> = prefixed.iter().map(Vec::as_slice).collect();
+                self.hasher.hash_many(&inputs)
+            }
+        }
+    }
+
+    /// The input hashed to get the parent of a left and a right child
+    fn node_input(&self, left: &[u8], right: &[u8]) -> Vec<u8> {
+        let (left, right) = match self.pair_order {
+            PairOrder::Sorted if right < left => (right, left),
+            _ => (left, right),
+        };
+        match self.mode {
+            TreeMode::Legacy => [left, right].concat(),
+            TreeMode::Rfc6962 => [&[NODE_PREFIX], left, right].concat(),
+        }
     }
 
     /// Build the Merkle Tree from a list of data blocks
     pub fn build(&mut self, data_blocks: Vec<&[u8]>) {
-        self.leaves = data_blocks
-            .into_iter()
-            .map(|data| MerkleNode {
-                hash: self.hasher.hash(data),
-                left: None,
-                right: None,
-            })
-            .collect();
+        self.levels.clear();
+        let leaf_hashes = self.hash_leaves(&data_blocks);
+        let Some(first) = leaf_hashes.first() else {
+            return;
+        };
+        self.digest_len = first.len();
+        assert!(self.digest_len > 0, "hash function returned an empty digest");
+        self.levels.push(self.flatten(leaf_hashes));
+        self.rehash(0, data_blocks.len());
+    }
 
-        let mut nodes = self.leaves.clone();
+    /// Append a leaf, recomputing only the path from it to the root
+    pub fn push_leaf(&mut self, data: &[u8]) {
+        let hash = self.hash_leaves(&[data]).remove(0);
+        if self.levels.is_empty() {
+            self.digest_len = hash.len();
+            assert!(self.digest_len > 0, "hash function returned an empty digest");
+            self.levels.push(Vec::new());
+        }
+        assert_eq!(hash.len(), self.digest_len, "hash function output length changed");
+        let index = self.leaf_count();
+        self.levels[0].extend_from_slice(&hash);
+        self.rehash(index, index + 1);
+    }
 
-        while nodes.len() > 1 {
-            let mut next_level = Vec::new();
+    /// Replace the leaf at `index`, recomputing only the path from it to the root
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than `leaf_count()`.
+    pub fn update_leaf(&mut self, index: usize, data: &[u8]) {
+        assert!(index < self.leaf_count(), "leaf index {} out of range", index);
+        let hash = self.flatten(self.hash_leaves(&[data]));
+        let digest_len = self.digest_len;
+        self.levels[0][index * digest_len..(index + 1) * digest_len].copy_from_slice(&hash);
+        self.rehash(index, index + 1);
+    }
 
-            for i in (0..nodes.len()).step_by(2) {
-                let left = Box::new(nodes[i].clone());
-                let right = if i + 1 < nodes.len() {
-                    Box::new(nodes[i + 1].clone())
-                } else {
-                    Box::new(nodes[i].clone()) // Duplicate last node if odd number
-                };
-                
-                let combined_hash = if left.hash.clone() < right.hash.clone() {
-                    [left.hash.clone(), right.hash.clone()].concat()
-                } else {
-                    [right.hash.clone(), left.hash.clone()].concat()
-                };
-                //let combined_hash = [left.hash.clone(), right.hash.clone()].concat();
-                let parent_hash = self.hasher.hash(&combined_hash);
-
-                next_level.push(MerkleNode {
-                    hash: parent_hash,
-                    left: Some(left),
-                    right: Some(right),
-                });
+    /// Remove the leaf at `index`, keeping the order of the others
+    ///
+    /// The tree is the same as one built from the remaining leaves. Every
+    /// leaf after `index` moves down one place, so this recomputes the nodes
+    /// above them and costs `O(n - index)`; see `swap_remove_leaf` for an
+    /// `O(log n)` removal that does not keep the order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than `leaf_count()`.
+    pub fn remove_leaf(&mut self, index: usize) {
+        let leaf_count = self.leaf_count();
+        assert!(index < leaf_count, "leaf index {} out of range", index);
+        if leaf_count == 1 {
+            self.levels.clear();
+            return;
+        }
+        let digest_len = self.digest_len;
+        self.levels[0].drain(index * digest_len..(index + 1) * digest_len);
+        // Every leaf from `index` on moved, and the last pair lost a child
+        let new_count = leaf_count - 1;
+        self.rehash(index.min(new_count - 1), new_count);
+    }
+
+    /// Remove the leaf at `index`, moving the last leaf into its place
+    ///
+    /// Like `Vec::swap_remove`, this does not keep the order of the leaves,
+    /// but only the paths from `index` and from the old last leaf to the
+    /// root are recomputed, so it is `O(log n)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than `leaf_count()`.
+    pub fn swap_remove_leaf(&mut self, index: usize) {
+        let leaf_count = self.leaf_count();
+        assert!(index < leaf_count, "leaf index {} out of range", index);
+        if leaf_count == 1 {
+            self.levels.clear();
+            return;
+        }
+        let digest_len = self.digest_len;
+        let last = self.levels[0].split_off((leaf_count - 1) * digest_len);
+        let new_count = leaf_count - 1;
+        if index < new_count {
+            self.levels[0][index * digest_len..(index + 1) * digest_len].copy_from_slice(&last);
+            self.rehash(index, index + 1);
+        }
+        // The pair that held the old last leaf lost a child
+        self.rehash(new_count - 1, new_count);
+    }
+
+    /// Recompute every node above the leaves `start..end`, up to the root
+    ///
+    /// Levels are resized to match the number of leaves, so this also covers
+    /// leaves that were added or removed at the end.
+    fn rehash(&mut self, mut start: usize, mut end: usize) {
+        let digest_len = self.digest_len;
+        let mut level = 0;
+
+        while self.level_len(level) > 1 {
+            let level_len = self.level_len(level);
+            let parent_len = level_len.div_ceil(2);
+            let promoted = self.promotes_last(level_len);
+            start /= 2;
+            end = end.div_ceil(2).min(parent_len);
+
+            // Hash every changed pair of the level in one batch
+            let paired_end = if promoted { end.min(parent_len - 1) } else { end };
+            let mut parents = self.flatten(self.hash_pairs(level, start..paired_end));
+            if promoted && end == parent_len {
+                parents.extend_from_slice(self.node(level, level_len - 1));
             }
 
-            nodes = next_level;
+            if self.levels.len() == level + 1 {
+                self.levels.push(Vec::new());
+            }
+            let next = &mut self.levels[level + 1];
+            next.resize(parent_len * digest_len, 0);
+            next[start * digest_len..end * digest_len].copy_from_slice(&parents);
+            level += 1;
         }
 
-        self.root = nodes.into_iter().next();
+        self.levels.truncate(level + 1);
+    }
+
+    /// Hash the children of the nodes `parents` of the level above `level`
+    fn hash_pairs(&self, level: usize, parents: Range<usize>) -> Vec<Vec<u8>> {
+        let level_len = self.level_len(level);
+        let combined_hashes: Vec<Vec<u8>> = parents
+            .map(|parent| {
+                let left = 2 * parent;
+                // Duplicate last node if odd number
+                let right = if left + 1 < level_len { left + 1 } else { left };
+                self.node_input(self.node(level, left), self.node(level, right))
+            })
+            .collect();
+        let inputs: Vec<&[u8]> = combined_hashes.iter().map(Vec::as_slice).collect();
+        self.hasher.hash_many(&inputs)
+    }
+
+    /// Concatenate equally sized hashes into one level buffer
+    fn flatten(&self, hashes: Vec<Vec<u8>>) -> Vec<u8> {
+        let mut nodes = Vec::with_capacity(hashes.len() * self.digest_len);
+        for hash in hashes {
+            assert_eq!(hash.len(), self.digest_len, "hash function output length changed");
+            nodes.extend_from_slice(&hash);
+        }
+        nodes
     }
 
     /// Get the Merkle root of the tree
     pub fn root_hash(&self) -> Option<&[u8]> {
-        self.root.as_ref().map(|node| node.hash.as_slice())
+        self.levels.last().map(Vec::as_slice)
     }
-    
+
+    /// Get the Merkle root as a `Digest`, for constant-time comparison
+    pub fn root(&self) -> Option<Digest> {
+        self.root_hash().and_then(Digest::from_slice)
+    }
+
     /// Generate a proof for a given leaf index
+    ///
+    /// The proof lists the sibling hash at each level from the leaf up, and
+    /// skips levels where the node was promoted without a sibling.
     pub fn generate_proof(&self, index: usize) -> Option<Vec<Vec<u8>>> {
-        if index >= self.leaves.len() {
+        if index >= self.leaf_count() {
             return None;
         }
 
         let mut proof = Vec::new();
         let mut current_index = index;
-        let mut nodes = self.leaves.clone();
-        
-        // Print initial leaf hashes
-        #[cfg(debug_assertions)]
-        {
-            println!("Leaf hashes:");
-            for node in &nodes {
-                println!("{:?}", utility::bytes_to_hex(&node.hash));
+
+        for level in 0..self.levels.len() - 1 {
+            let level_len = self.level_len(level);
+            if !(current_index == level_len - 1 && self.promotes_last(level_len)) {
+                // Duplicate last node if odd number
+                let sibling = (current_index ^ 1).min(level_len - 1);
+                proof.push(self.node(level, sibling).to_vec());
+            }
+            current_index /= 2;
+        }
+
+        Some(proof)
+    }
+
+    /// The number of siblings in a proof for leaf `index` of `leaf_count` leaves
+    ///
+    /// One per level, except levels where this tree's policy promotes the
+    /// node without a sibling.
+    pub fn proof_len(&self, index: usize, leaf_count: usize) -> usize {
+        let mut len = 0;
+        let mut index = index;
+        let mut level_len = leaf_count;
+        while level_len > 1 {
+            if !(index == level_len - 1 && self.promotes_last(level_len)) {
+                len += 1;
             }
+            index /= 2;
+            level_len = level_len.div_ceil(2);
         }
+        len
+    }
 
-        while nodes.len() > 1 {
-            let mut next_level = Vec::new();
+    /// Verify a proof for a given leaf and expected root
+    ///
+    /// `index` is the position the leaf claims in a tree of `leaf_count`
+    /// leaves. With `PairOrder::Positional` its bits say on which side each
+    /// sibling goes, so a proof for one position fails for any other; with
+    /// `PairOrder::Sorted` only the proof length depends on them. The tree
+    /// size tells at which levels a promoted node has no sibling.
+    pub fn verify_proof(
+        &self,
+        leaf: &[u8],
+        index: usize,
+        leaf_count: usize,
+        proof: impl AsRef<[Vec<u8>]>,
+        expected_root: &[u8],
+    ) -> bool {
+        if index >= leaf_count {
+            return false;
+        }
+
+        let mut hash = self.hash_leaves(&[leaf]).remove(0);
 
-            for i in (0..nodes.len()).step_by(2) {
-                let left = &nodes[i];
-                let right = if i + 1 < nodes.len() {
-                    &nodes[i + 1]
+        let mut index = index;
+        let mut level_len = leaf_count;
+        let mut siblings = proof.as_ref().iter();
+        while level_len > 1 {
+            if index == level_len - 1 && self.promotes_last(level_len) {
+                index /= 2;
+                level_len = level_len.div_ceil(2);
+                continue;
+            }
+            let Some(sibling_hash) = siblings.next() else {
+                return false;
+            };
+            let combined_hash = if index.is_multiple_of(2) {
+                self.node_input(&hash, sibling_hash)
+            } else {
+                self.node_input(sibling_hash, &hash)
+            };
+            hash = self.hasher.hash(&combined_hash);
+            index /= 2;
+            level_len = level_len.div_ceil(2);
+        }
+
+        // Every sibling must have been used
+        siblings.next().is_none() && constant_time_eq(&hash, expected_root)
+    }
+
+    /// Generate one proof for several leaves
+    ///
+    /// Returns `None` if `indices` is empty or any index is out of range.
+    /// Duplicate indices are proven once.
+    pub fn generate_multiproof(&self, indices: &[usize]) -> Option<MultiProof> {
+        let leaf_count = self.leaf_count();
+        let mut known: Vec<usize> = indices.to_vec();
+        known.sort_unstable();
+        known.dedup();
+        if known.is_empty() || known[known.len() - 1] >= leaf_count {
+            return None;
+        }
+        let proven = known.clone();
+
+        let mut hashes = Vec::new();
+        for level in 0..self.levels.len() - 1 {
+            let level_len = self.level_len(level);
+            let mut parents = Vec::new();
+            let mut i = 0;
+            while i < known.len() {
+                let index = known[i];
+                let promoted = index == level_len - 1 && self.promotes_last(level_len);
+                if !promoted && index.is_multiple_of(2) && known.get(i + 1) == Some(&(index + 1)) {
+                    // Both children are known to the verifier
+                    i += 1;
+                } else if !promoted && index ^ 1 < level_len {
+                    hashes.push(self.node(level, index ^ 1).to_vec());
+                }
+                parents.push(index / 2);
+                i += 1;
+            }
+            known = parents;
+        }
+
+        Some(MultiProof { indices: proven, leaf_count, hashes })
+    }
+
+    /// Verify a proof from `generate_multiproof`
+    ///
+    /// `leaves` holds the data of the leaves at `proof.indices`, in the same order.
+    pub fn verify_multiproof(
+        &self,
+        leaves: &[&[u8]],
+        proof: &MultiProof,
+        expected_root: &[u8],
+    ) -> bool {
+        let ascending = proof.indices.windows(2).all(|pair| pair[0] < pair[1]);
+        let in_range = proof.indices.last().is_some_and(|&last| last < proof.leaf_count);
+        if leaves.len() != proof.indices.len() || !ascending || !in_range {
+            return false;
+        }
+
+        let mut known: Vec<(usize, Vec<u8>)> =
+            proof.indices.iter().copied().zip(self.hash_leaves(leaves)).collect();
+        let mut siblings = proof.hashes.iter();
+        let mut level_len = proof.leaf_count;
+
+        while level_len > 1 {
+            let mut parents = Vec::new();
+            let mut i = 0;
+            while i < known.len() {
+                let (index, ref hash) = known[i];
+                if index == level_len - 1 && self.promotes_last(level_len) {
+                    parents.push((index / 2, hash.clone()));
+                    i += 1;
+                    continue;
+                }
+
+                let pair_known = known.get(i + 1).is_some_and(|next| next.0 == index + 1);
+                let sibling = if index.is_multiple_of(2) && pair_known {
+                    i += 1;
+                    known[i].1.as_slice()
+                } else if index ^ 1 < level_len {
+                    match siblings.next() {
+                        Some(sibling) => sibling.as_slice(),
+                        None => return false,
+                    }
                 } else {
-                    left
+                    // Duplicate last node if odd number
+                    hash.as_slice()
                 };
-                
-                // Print the hashes being combined
-                #[cfg(debug_assertions)]
-                println!("Combining hashes: {:?} and {:?}", utility::bytes_to_hex(&left.hash), utility::bytes_to_hex(&right.hash));
-
-                if i == current_index || i + 1 == current_index {
-                    if i == current_index {
-                        proof.push(right.hash.clone());
-                        #[cfg(debug_assertions)]
-                        println!("Adding to proof: {:?}", utility::bytes_to_hex(&right.hash));
-                    } else {
-                        proof.push(left.hash.clone());
-                        #[cfg(debug_assertions)]
-                        println!("Adding to proof: {:?}", utility::bytes_to_hex(&left.hash));
-                    }
-                    current_index /= 2;
-                }
 
-                let combined_hash = if left.hash.clone() < right.hash.clone() {
-                    [left.hash.clone(), right.hash.clone()].concat()
+                let combined_hash = if index.is_multiple_of(2) {
+                    self.node_input(hash, sibling)
                 } else {
-                    [right.hash.clone(), left.hash.clone()].concat()
+                    self.node_input(sibling, hash)
                 };
-                //let combined_hash = [left.hash.clone(), right.hash.clone()].concat();
-                let parent_hash = self.hasher.hash(&combined_hash);
-                
-                // Print the parent hash
-                #[cfg(debug_assertions)]
-                println!("Parent hash: {:?}", utility::bytes_to_hex(&parent_hash));
-
-                next_level.push(MerkleNode {
-                    hash: parent_hash,
-                    left: None,
-                    right: None,
-                });
+                parents.push((index / 2, self.hasher.hash(&combined_hash)));
+                i += 1;
             }
+            known = parents;
+            level_len = level_len.div_ceil(2);
+        }
 
-            nodes = next_level;
+        // Every sibling must have been used
+        siblings.next().is_none() && constant_time_eq(&known[0].1, expected_root)
+    }
+
+    /// Generate an RFC 6962 consistency proof that the first `old_size`
+    /// leaves of this tree form the tree whose root was published earlier
+    ///
+    /// Only trees with the RFC 6962 shape can be compared at two sizes, so
+    /// this returns `None` for `OddNodePolicy::Duplicate`, as well as for an
+    /// `old_size` of 0 or larger than the tree.
+    pub fn consistency_proof(&self, old_size: usize) -> Option<Vec<Vec<u8>>> {
+        let new_size = self.leaf_count();
+        let duplicating = self.odd_node_policy == OddNodePolicy::Duplicate;
+        if duplicating || old_size == 0 || old_size > new_size {
+            return None;
         }
 
+        let mut proof = Vec::new();
+        self.consistency_subproof(old_size, 0, new_size, true, &mut proof);
         Some(proof)
     }
 
-    /// Verify a proof for a given leaf and expected root
-    pub fn verify_proof(&self, leaf: &[u8], proof: Vec<Vec<u8>>, expected_root: &[u8]) -> bool {
-        let mut hash = self.hasher.hash(leaf);
-        #[cfg(debug_assertions)]
-        println!("Initial leaf hash: {:?}", utility::bytes_to_hex(&hash));
-
-        #[cfg(debug_assertions)]
-        {
-        for (i, sibling_hash) in proof.iter().enumerate() {
-        println!("Sibling hash {}: {:?}", i, utility::bytes_to_hex(sibling_hash));
-        
-            let combined_hash = if hash < *sibling_hash {
-                [hash.clone(), sibling_hash.clone()].concat()
-            } else {
-                [sibling_hash.clone(), hash.clone()].concat()
-            };
-            //let combined_hash = [hash.clone(), sibling_hash.clone()].concat()
-            hash = self.hasher.hash(&combined_hash);
-            println!("Combined hash {}: {:?}", i, utility::bytes_to_hex(&hash));
+    /// SUBPROOF from RFC 6962, section 2.1.2, for the leaves `start..end`
+    fn consistency_subproof(
+        &self,
+        old_size: usize,
+        start: usize,
+        end: usize,
+        complete: bool,
+        proof: &mut Vec<Vec<u8>>,
+    ) {
+        let size = end - start;
+        if old_size == size {
+            // The verifier already has the root of a complete old tree
+            if !complete {
+                proof.push(self.subtree_hash(start, end).to_vec());
+            }
+            return;
         }
+
+        let split = split_point(size);
+        if old_size <= split {
+            self.consistency_subproof(old_size, start, start + split, complete, proof);
+            proof.push(self.subtree_hash(start + split, end).to_vec());
+        } else {
+            self.consistency_subproof(old_size - split, start + split, end, false, proof);
+            proof.push(self.subtree_hash(start, start + split).to_vec());
         }
-        
-        #[cfg(not(debug_assertions))]
-        {
-        for sibling_hash in proof.iter() {
+    }
 
-            let combined_hash = if hash < *sibling_hash {
-                [hash.clone(), sibling_hash.clone()].concat()
-            } else {
-                [sibling_hash.clone(), hash.clone()].concat()
-            };
-            //let combined_hash = [hash.clone(), sibling_hash.clone()].concat()
-            hash = self.hasher.hash(&combined_hash);
+    /// The hash of the node covering exactly the leaves `start..end`
+    ///
+    /// Valid for the ranges the RFC 6962 split produces: `start` is aligned to
+    /// the subtree size, and `end` is either a power of two past it or the
+    /// last leaf.
+    fn subtree_hash(&self, start: usize, end: usize) -> &[u8] {
+        let level = (end - start).next_power_of_two().trailing_zeros() as usize;
+        self.node(level, start >> level)
+    }
+
+    /// Verify a consistency proof from `consistency_proof`
+    ///
+    /// Follows RFC 9162, section 2.1.4.2: checks that the tree with root
+    /// `new_root` and `new_size` leaves starts with the `old_size` leaves of
+    /// the tree with root `old_root`.
+    pub fn verify_consistency(
+        &self,
+        old_root: &[u8],
+        old_size: usize,
+        new_root: &[u8],
+        new_size: usize,
+        proof: Vec<Vec<u8>>,
+    ) -> bool {
+        let duplicating = self.odd_node_policy == OddNodePolicy::Duplicate;
+        if duplicating || old_size == 0 || old_size > new_size {
+            return false;
         }
+        if old_size == new_size {
+            return proof.is_empty() && constant_time_eq(old_root, new_root);
         }
-        
-        #[cfg(debug_assertions)]
-        {
-            println!("Final computed hash: {:?}", utility::bytes_to_hex(&hash));
-            println!("Expected root hash: {:?}", utility::bytes_to_hex(expected_root));
+
+        // A complete old tree is itself the first node of the path
+        let mut path = proof.iter().map(Vec::as_slice);
+        let first = if old_size.is_power_of_two() { Some(old_root) } else { path.next() };
+        let Some(first) = first else {
+            return false;
+        };
+
+        let mut old_index = old_size - 1;
+        let mut new_index = new_size - 1;
+        while old_index & 1 == 1 {
+            old_index >>= 1;
+            new_index >>= 1;
+        }
+
+        let mut old_hash = first.to_vec();
+        let mut new_hash = first.to_vec();
+        for sibling in path {
+            if new_index == 0 {
+                return false;
+            }
+            if old_index & 1 == 1 || old_index == new_index {
+                old_hash = self.hasher.hash(&self.node_input(sibling, &old_hash));
+                new_hash = self.hasher.hash(&self.node_input(sibling, &new_hash));
+                if old_index & 1 == 0 {
+                    while old_index & 1 == 0 && old_index != 0 {
+                        old_index >>= 1;
+                        new_index >>= 1;
+                    }
+                }
+            } else {
+                new_hash = self.hasher.hash(&self.node_input(&new_hash, sibling));
+            }
+            old_index >>= 1;
+            new_index >>= 1;
         }
 
-        hash == expected_root
+        new_index == 0
+            && constant_time_eq(&old_hash, old_root)
+            && constant_time_eq(&new_hash, new_root)
+    }
+}
+
+/// The largest power of two less than `size`, where RFC 6962 splits a tree
+fn split_point(size: usize) -> usize {
+    debug_assert!(size > 1);
+    size.next_power_of_two() / 2
+}
+
+/// Number of leaves or pairs each parallel task hashes with one `hash_many` call
+#[cfg(feature = "parallel")]
+const PARALLEL_BATCH: usize = 1024;
+
+#[cfg(feature = "parallel")]
+impl<H: HashFunction + Sync> MerkleTree<H> {
+    /// Build the Merkle Tree like `build`, hashing on all cores
+    ///
+    /// The leaves, and then the pairs of each level, are split into batches
+    /// hashed on the rayon thread pool. The tree is identical to the one
+    /// `build` produces.
+    pub fn build_parallel(&mut self, data_blocks: Vec<&[u8]>) {
+        self.levels.clear();
+        let leaf_hashes: Vec<Vec<u8>> = data_blocks
+            .par_chunks(PARALLEL_BATCH)
+            .flat_map_iter(|batch| self.hash_leaves(batch))
+            .collect();
+        let Some(first) = leaf_hashes.first() else {
+            return;
+        };
+        self.digest_len = first.len();
+        assert!(self.digest_len > 0, "hash function returned an empty digest");
+        self.levels.push(self.flatten(leaf_hashes));
+
+        let mut level = 0;
+        while self.level_len(level) > 1 {
+            let level_len = self.level_len(level);
+            let promoted = self.promotes_last(level_len);
+            let paired_len = if promoted { level_len / 2 } else { level_len.div_ceil(2) };
+
+            let hashes: Vec<Vec<u8>> = (0..paired_len.div_ceil(PARALLEL_BATCH))
+                .into_par_iter()
+                .flat_map_iter(|batch| {
+                    let start = batch * PARALLEL_BATCH;
+                    self.hash_pairs(level, start..(start + PARALLEL_BATCH).min(paired_len))
+                })
+                .collect();
+            let mut parents = self.flatten(hashes);
+            if promoted {
+                parents.extend_from_slice(self.node(level, level_len - 1));
+            }
+            self.levels.push(parents);
+            level += 1;
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::sha256::sha256;
-    use crate::merkle::MerkleTree;
-    use crate::Sha256Hasher;
+    use crate::merkle::{MerkleTree, MultiProof, OddNodePolicy, PairOrder, TreeMode};
+    use crate::utility::{bytes_to_hex, hex_to_bytes};
+    use crate::sha256::Sha256Hasher;
     use crate::sha256;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use alloc::format;
     
         #[test]
     fn test_merkle_tree_single_block() {
@@ -261,5 +805,404 @@ mod tests {
 
         // Expected root should be None
         assert!(merkle_tree.root_hash().is_none());
+        assert!(merkle_tree.root().is_none());
+    }
+
+    #[test]
+    fn test_merkle_tree_rfc6962_prefixes() {
+        let mut merkle_tree = MerkleTree::new(Sha256Hasher).with_mode(TreeMode::Rfc6962);
+        merkle_tree.build(vec![b"block1"]);
+
+        let expected_root = sha256(&[b"\x00".as_slice(), b"block1"].concat()).to_vec();
+        assert_eq!(merkle_tree.root_hash().unwrap(), expected_root);
+    }
+
+    #[test]
+    fn test_merkle_tree_leaf_cannot_pose_as_node() {
+        let forged_leaf = [sha256(b"block2"), sha256(b"block3")].concat();
+
+        for (mode, collides) in [(TreeMode::Legacy, true), (TreeMode::Rfc6962, false)] {
+            let mut honest = MerkleTree::new(Sha256Hasher).with_mode(mode);
+            honest.build(vec![b"block2", b"block3"]);
+            let mut forged = MerkleTree::new(Sha256Hasher).with_mode(mode);
+            forged.build(vec![&forged_leaf]);
+
+            assert_eq!(honest.root() == forged.root(), collides, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_merkle_tree_rfc6962_proofs() {
+        let data_blocks: Vec<&[u8]> = vec![b"block1", b"block2", b"block3", b"block4", b"block5"];
+        let mut merkle_tree = MerkleTree::new(Sha256Hasher).with_mode(TreeMode::Rfc6962);
+        merkle_tree.build(data_blocks.clone());
+        let root = merkle_tree.root_hash().unwrap().to_vec();
+
+        for (index, block) in data_blocks.iter().enumerate() {
+            let proof = merkle_tree.generate_proof(index).unwrap();
+            assert!(merkle_tree.verify_proof(block, index, 5, proof.clone(), &root));
+            assert!(!MerkleTree::new(Sha256Hasher).verify_proof(block, index, 5, proof, &root));
+        }
+    }
+
+    #[test]
+    fn test_merkle_tree_positional_proofs_bind_index() {
+        let data_blocks: Vec<&[u8]> = vec![b"block1", b"block2", b"block3", b"block4", b"block5"];
+        let mut merkle_tree = MerkleTree::new(Sha256Hasher);
+        merkle_tree.build(data_blocks.clone());
+        let root = merkle_tree.root_hash().unwrap().to_vec();
+
+        for (index, block) in data_blocks.iter().enumerate() {
+            let proof = merkle_tree.generate_proof(index).unwrap();
+            assert!(merkle_tree.verify_proof(block, index, 5, proof.clone(), &root));
+            for other in (0..5).filter(|&other| other != index) {
+                assert!(!merkle_tree.verify_proof(block, other, 5, proof.clone(), &root));
+            }
+            assert!(!merkle_tree.verify_proof(block, index + 8, 5, proof, &root));
+        }
+    }
+
+    #[test]
+    fn test_merkle_tree_sorted_pairs() {
+        let data_blocks: Vec<&[u8]> = vec![b"block1", b"block2", b"block3", b"block4"];
+        let mut merkle_tree = MerkleTree::new(Sha256Hasher).with_pair_order(PairOrder::Sorted);
+        merkle_tree.build(data_blocks.clone());
+
+        // Swapping the leaves within each pair keeps a sorted root
+        let mut swapped = MerkleTree::new(Sha256Hasher).with_pair_order(PairOrder::Sorted);
+        swapped.build(vec![b"block2", b"block1", b"block4", b"block3"]);
+        assert_eq!(merkle_tree.root(), swapped.root());
+
+        let root = merkle_tree.root_hash().unwrap().to_vec();
+        let proof = merkle_tree.generate_proof(2).unwrap();
+        assert!(merkle_tree.verify_proof(b"block3", 2, 4, proof.clone(), &root));
+        assert!(merkle_tree.verify_proof(b"block3", 3, 4, proof, &root));
+    }
+
+    #[test]
+    fn test_merkle_tree_incremental_updates() {
+        let blocks: Vec<Vec<u8>> = (0..12u8).map(|i| vec![i; 10]).collect();
+        let policies = [OddNodePolicy::Duplicate, OddNodePolicy::Promote];
+
+        for policy in policies {
+            let new_tree = || {
+                MerkleTree::new(Sha256Hasher)
+                    .with_mode(TreeMode::Rfc6962)
+                    .with_odd_node_policy(policy)
+            };
+            let rebuilt = |data: &[Vec<u8>]| {
+                let mut tree = new_tree();
+                tree.build(data.iter().map(Vec::as_slice).collect());
+                tree
+            };
+            let assert_same = |tree: &MerkleTree<Sha256Hasher>, data: &[Vec<u8>]| {
+                let expected = rebuilt(data);
+                let context = format!("{:?}, {} leaves", policy, data.len());
+                assert_eq!(tree.root_hash(), expected.root_hash(), "{}", context);
+                for index in 0..data.len() {
+                    assert_eq!(tree.generate_proof(index), expected.generate_proof(index));
+                }
+            };
+
+            // Grow one leaf at a time from empty
+            let mut merkle_tree = new_tree();
+            for len in 1..=blocks.len() {
+                merkle_tree.push_leaf(&blocks[len - 1]);
+                assert_same(&merkle_tree, &blocks[..len]);
+            }
+
+            let mut data = blocks.clone();
+            for index in [0, 5, 11] {
+                data[index] = b"changed".to_vec();
+                merkle_tree.update_leaf(index, b"changed");
+                assert_same(&merkle_tree, &data);
+            }
+
+            // Shrink back to empty, from the middle and both ends, with the
+            // remaining leaves in order and with the last one moved in
+            let mut swapped = rebuilt(&data);
+            let mut swapped_data = data.clone();
+            for index in [11, 0, 4, 4, 7, 3, 0, 3, 1, 0, 1, 0] {
+                data.remove(index);
+                merkle_tree.remove_leaf(index);
+                assert_same(&merkle_tree, &data);
+
+                swapped_data.swap_remove(index);
+                swapped.swap_remove_leaf(index);
+                assert_same(&swapped, &swapped_data);
+            }
+            assert!(merkle_tree.root_hash().is_none());
+            assert!(swapped.root_hash().is_none());
+        }
+    }
+
+    #[test]
+    fn test_merkle_tree_multiproofs() {
+        let blocks: Vec<Vec<u8>> = (0..100u8).map(|i| vec![i; 10]).collect();
+        let data_blocks: Vec<&[u8]> = blocks.iter().map(Vec::as_slice).collect();
+        let policies = [OddNodePolicy::Duplicate, OddNodePolicy::Promote];
+        let orders = [PairOrder::Positional, PairOrder::Sorted];
+        // Exact sizes under both policies; siblings shared between paths, or
+        // known from the proven leaves, are only sent once or not at all
+        let cases: [(&[usize], usize); 6] = [
+            (&[0], 7),
+            (&[99], 4),
+            // Adjacent leaves share every sibling above the leaves
+            (&[0, 1], 6),
+            (&[3, 4, 5, 6], 7),
+            (&[0, 1, 50, 98, 99], 11),
+            (&[10, 20, 30, 40], 16),
+        ];
+
+        for (policy, order) in policies.into_iter().zip(orders) {
+            let mut merkle_tree = MerkleTree::new(Sha256Hasher)
+                .with_mode(TreeMode::Rfc6962)
+                .with_pair_order(order)
+                .with_odd_node_policy(policy);
+            merkle_tree.build(data_blocks.clone());
+            let root = merkle_tree.root_hash().unwrap().to_vec();
+
+            for (indices, hash_count) in cases {
+                let proof = merkle_tree.generate_multiproof(indices).unwrap();
+                let leaves: Vec<&[u8]> = indices.iter().map(|&index| data_blocks[index]).collect();
+                assert!(merkle_tree.verify_multiproof(&leaves, &proof, &root), "{:?}", indices);
+                assert_eq!(proof.hashes.len(), hash_count, "{:?}, {:?}", policy, indices);
+
+                let separate: usize =
+                    indices.iter().map(|&i| merkle_tree.generate_proof(i).unwrap().len()).sum();
+                if indices.len() > 1 {
+                    assert!(proof.hashes.len() < separate, "{:?}, {:?}", policy, indices);
+                }
+
+                let mut tampered = leaves.clone();
+                tampered[0] = b"other";
+                assert!(!merkle_tree.verify_multiproof(&tampered, &proof, &root));
+                assert!(!merkle_tree.verify_multiproof(&leaves[1..], &proof, &root));
+            }
+        }
+
+        let mut merkle_tree = MerkleTree::new(Sha256Hasher);
+        merkle_tree.build(data_blocks.clone());
+        let root = merkle_tree.root_hash().unwrap().to_vec();
+
+        // A contiguous range needs only the hashes along its two edges
+        let range: Vec<usize> = (16..48).collect();
+        let proof = merkle_tree.generate_multiproof(&range).unwrap();
+        assert_eq!(proof.hashes.len(), 3);
+        let separate: usize =
+            range.iter().map(|&i| merkle_tree.generate_proof(i).unwrap().len()).sum();
+        assert_eq!(separate, 32 * 7);
+        let leaves: Vec<&[u8]> = range.iter().map(|&index| data_blocks[index]).collect();
+        assert!(merkle_tree.verify_multiproof(&leaves, &proof, &root));
+
+        // Positions are bound into the root
+        let moved = MultiProof { indices: (17..49).collect(), ..proof };
+        assert!(!merkle_tree.verify_multiproof(&leaves, &moved, &root));
+
+        assert!(merkle_tree.generate_multiproof(&[]).is_none());
+        assert!(merkle_tree.generate_multiproof(&[5, 100]).is_none());
+    }
+
+    #[test]
+    fn test_merkle_tree_consistency_proofs() {
+        let blocks: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i; 10]).collect();
+        let new_tree = || {
+            MerkleTree::new(Sha256Hasher)
+                .with_mode(TreeMode::Rfc6962)
+                .with_odd_node_policy(OddNodePolicy::RFC6962_SPLIT)
+        };
+
+        let mut merkle_tree = new_tree();
+        let mut roots = vec![Vec::new()];
+        for block in &blocks {
+            merkle_tree.push_leaf(block);
+            roots.push(merkle_tree.root_hash().unwrap().to_vec());
+        }
+        let data_blocks: Vec<&[u8]> = blocks.iter().map(Vec::as_slice).collect();
+        assert_eq!(roots[20], rfc6962_root(&data_blocks));
+
+        for new_size in 1..=20 {
+            let mut grown = new_tree();
+            grown.build(data_blocks[..new_size].to_vec());
+
+            for old_size in 1..=new_size {
+                let proof = grown.consistency_proof(old_size).unwrap();
+                let (old_root, new_root) = (&roots[old_size], &roots[new_size]);
+                assert!(
+                    grown.verify_consistency(old_root, old_size, new_root, new_size, proof.clone()),
+                    "{} -> {}",
+                    old_size,
+                    new_size
+                );
+
+                // A rewritten old tree must fail
+                let forged = roots[old_size % 20 + 1].as_slice();
+                assert!(!grown.verify_consistency(forged, old_size, new_root, new_size, proof));
+            }
+        }
+
+        assert!(merkle_tree.consistency_proof(0).is_none());
+        assert!(merkle_tree.consistency_proof(21).is_none());
+        let mut duplicating = MerkleTree::new(Sha256Hasher);
+        duplicating.build(data_blocks);
+        assert!(duplicating.consistency_proof(5).is_none());
+    }
+
+    #[test]
+    fn test_merkle_tree_consistency_rfc6962_vectors() {
+        // Test vectors from the Certificate Transparency reference implementation
+        let leaves = [
+            "", "00", "10", "2021", "3031", "40414243", "5051525354555657",
+            "606162636465666768696a6b6c6d6e6f",
+        ]
+        .map(|leaf| hex_to_bytes(leaf).unwrap());
+        let mut merkle_tree = MerkleTree::new(Sha256Hasher)
+            .with_mode(TreeMode::Rfc6962)
+            .with_odd_node_policy(OddNodePolicy::RFC6962_SPLIT);
+        merkle_tree.build(leaves.iter().map(Vec::as_slice).collect());
+
+        let root = merkle_tree.root_hash().unwrap();
+        assert_eq!(
+            bytes_to_hex(root),
+            "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328"
+        );
+
+        let proof = merkle_tree.consistency_proof(1).unwrap();
+        let expected = [
+            "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+            "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+            "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+        ];
+        assert_eq!(proof.iter().map(|hash| bytes_to_hex(hash)).collect::<Vec<_>>(), expected);
+
+        let old_root = sha256(&[0x00]);
+        assert!(merkle_tree.verify_consistency(&old_root, 1, root, 8, proof));
+    }
+
+    #[test]
+    fn test_merkle_tree_many_leaves() {
+        let blocks: Vec<[u8; 4]> = (0..1000u32).map(u32::to_be_bytes).collect();
+        let data_blocks: Vec<&[u8]> = blocks.iter().map(|block| block.as_slice()).collect();
+        let mut merkle_tree = MerkleTree::new(Sha256Hasher).with_mode(TreeMode::Rfc6962);
+        merkle_tree.build(data_blocks.clone());
+        let root = merkle_tree.root_hash().unwrap().to_vec();
+
+        assert_eq!(merkle_tree.leaf_count(), 1000);
+        for index in [0, 1, 511, 512, 998, 999] {
+            let proof = merkle_tree.generate_proof(index).unwrap();
+            assert_eq!(proof.len(), 10);
+            assert!(merkle_tree.verify_proof(data_blocks[index], index, 1000, proof, &root));
+        }
+        assert!(merkle_tree.generate_proof(1000).is_none());
+
+        // Rebuilding replaces the previous tree entirely
+        merkle_tree.build(vec![b"block1"]);
+        assert_eq!(merkle_tree.leaf_count(), 1);
+        assert_eq!(merkle_tree.generate_proof(0), Some(vec![]));
+    }
+
+    /// Merkle Tree Hash of RFC 6962, section 2.1, for checking the split policy
+    fn rfc6962_root(leaves: &[&[u8]]) -> Vec<u8> {
+        if leaves.len() == 1 {
+            return sha256(&[&[0x00], leaves[0]].concat()).to_vec();
+        }
+        let (left, right) = leaves.split_at(super::split_point(leaves.len()));
+        sha256(&[&[0x01], rfc6962_root(left).as_slice(), &rfc6962_root(right)].concat()).to_vec()
+    }
+
+    #[test]
+    fn test_merkle_tree_odd_node_policies() {
+        let blocks: Vec<Vec<u8>> = (0..13u8).map(|i| vec![i; 10]).collect();
+
+        for leaf_count in 1..=blocks.len() {
+            let data_blocks: Vec<&[u8]> = blocks[..leaf_count].iter().map(Vec::as_slice).collect();
+
+            for policy in [OddNodePolicy::Duplicate, OddNodePolicy::Promote] {
+                let mut merkle_tree = MerkleTree::new(Sha256Hasher)
+                    .with_mode(TreeMode::Rfc6962)
+                    .with_odd_node_policy(policy);
+                merkle_tree.build(data_blocks.clone());
+                let root = merkle_tree.root_hash().unwrap().to_vec();
+
+                if policy != OddNodePolicy::Duplicate {
+                    assert_eq!(root, rfc6962_root(&data_blocks), "{} leaves", leaf_count);
+                }
+
+                for (index, block) in data_blocks.iter().enumerate() {
+                    let proof = merkle_tree.generate_proof(index).unwrap();
+                    assert!(
+                        merkle_tree.verify_proof(block, index, leaf_count, proof.clone(), &root),
+                        "{:?}, leaf {} of {}",
+                        policy,
+                        index,
+                        leaf_count
+                    );
+                    assert!(!merkle_tree.verify_proof(b"other", index, leaf_count, proof, &root));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_merkle_tree_duplicate_policy_collision() {
+        let mut three = MerkleTree::new(Sha256Hasher);
+        three.build(vec![b"block1", b"block2", b"block3"]);
+        let mut four = MerkleTree::new(Sha256Hasher);
+        four.build(vec![b"block1", b"block2", b"block3", b"block3"]);
+        assert_eq!(three.root(), four.root());
+
+        let mut three = MerkleTree::new(Sha256Hasher).with_odd_node_policy(OddNodePolicy::Promote);
+        three.build(vec![b"block1", b"block2", b"block3"]);
+        let mut four = MerkleTree::new(Sha256Hasher).with_odd_node_policy(OddNodePolicy::Promote);
+        four.build(vec![b"block1", b"block2", b"block3", b"block3"]);
+        assert_ne!(three.root(), four.root());
+    }
+
+    #[test]
+    fn test_merkle_tree_default_hash_many() {
+        // A hasher relying on the default per-item `hash_many`
+        struct PlainSha256;
+        impl crate::merkle::HashFunction for PlainSha256 {
+            fn hash(&self, input: &[u8]) -> Vec<u8> {
+                sha256(input).to_vec()
+            }
+        }
+
+        let data_blocks: Vec<&[u8]> = vec![b"block1", b"block2", b"block3", b"block4", b"block5"];
+
+        let mut batched = MerkleTree::new(Sha256Hasher);
+        batched.build(data_blocks.clone());
+        let mut plain = MerkleTree::new(PlainSha256);
+        plain.build(data_blocks);
+
+        assert_eq!(batched.root_hash(), plain.root_hash());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_merkle_tree_parallel_build_matches_build() {
+        let blocks: Vec<[u8; 4]> = (0..5000u32).map(u32::to_be_bytes).collect();
+        let data_blocks: Vec<&[u8]> = blocks.iter().map(|block| block.as_slice()).collect();
+
+        for policy in [OddNodePolicy::Duplicate, OddNodePolicy::Promote] {
+            for (mode, pair_order) in [
+                (TreeMode::Legacy, PairOrder::Sorted),
+                (TreeMode::Rfc6962, PairOrder::Positional),
+            ] {
+                let tree = || {
+                    MerkleTree::new(Sha256Hasher)
+                        .with_mode(mode)
+                        .with_pair_order(pair_order)
+                        .with_odd_node_policy(policy)
+                };
+                for leaf_count in (0..=20).chain([1023, 1024, 1025, 2049, 5000]) {
+                    let mut sequential = tree();
+                    sequential.build(data_blocks[..leaf_count].to_vec());
+                    let mut parallel = tree();
+                    parallel.build_parallel(data_blocks[..leaf_count].to_vec());
+                    assert!(parallel.levels == sequential.levels, "{} leaves", leaf_count);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,380 @@
+use alloc::vec::Vec;
+
+use crate::digest::Digest;
+use crate::merkle::{HashFunction, LEAF_PREFIX, NODE_PREFIX};
+use crate::utility::constant_time_eq;
+
+/// Merkle Mountain Range: an append-only list of leaves
+///
+/// The leaves form a row of perfect binary trees ("mountains") of strictly
+/// decreasing size, one for each set bit of the leaf count. Appending a leaf
+/// only merges equal-sized mountains at the right end, so it costs
+/// `O(log n)` hashes and never changes existing nodes. The root bags the
+/// mountain peaks from right to left.
+///
+/// Leaves are `H(0x00 || data)` and nodes `H(0x01 || left || right)`.
+pub struct MerkleMountainRange<H: HashFunction> {
+    hasher: H,
+    /// Every node in post-order, the order in which they are appended
+    nodes: Vec<Vec<u8>>,
+    leaf_count: usize,
+}
+
+/// Proof that a leaf is part of a `MerkleMountainRange`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MmrProof {
+    /// Position of the leaf
+    pub leaf_index: usize,
+    /// Number of leaves in the range the proof was made for
+    pub leaf_count: usize,
+    /// Sibling hashes from the leaf up to its mountain's peak
+    pub siblings: Vec<Vec<u8>>,
+    /// The peaks of the other mountains, left to right
+    pub peaks: Vec<Vec<u8>>,
+}
+
+/// Proof that a `MerkleMountainRange` only grew between two sizes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MmrConsistencyProof {
+    /// The peaks at the old size, left to right
+    pub old_peaks: Vec<Vec<u8>>,
+    /// Roots of the subtrees made only of leaves appended since, in the
+    /// order they are needed to rebuild the new peaks
+    pub nodes: Vec<Vec<u8>>,
+}
+
+impl<H: HashFunction> MerkleMountainRange<H> {
+    /// Create an empty range with the given hasher
+    pub fn new(hasher: H) -> Self {
+        MerkleMountainRange { hasher, nodes: Vec::new(), leaf_count: 0 }
+    }
+
+    /// The number of leaves appended so far
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Append a leaf, returning its index
+    pub fn push(&mut self, data: &[u8]) -> usize {
+        let index = self.leaf_count;
+        let mut hash = self.hasher.hash(&[&[LEAF_PREFIX], data].concat());
+
+        // Each trailing one bit of the old count is a mountain of the new
+        // one's height, to merge with
+        for height in 0..index.trailing_ones() {
+            let left = &self.nodes[self.nodes.len() + 1 - (2 << height)];
+            let parent = self.hash_node(left, &hash);
+            self.nodes.push(hash);
+            hash = parent;
+        }
+        self.nodes.push(hash);
+
+        self.leaf_count += 1;
+        index
+    }
+
+    /// Get the root: the peaks bagged from right to left
+    ///
+    /// Returns `None` for an empty range.
+    pub fn root_hash(&self) -> Option<Vec<u8>> {
+        self.bag(self.peaks(self.leaf_count))
+    }
+
+    /// Get the root as a `Digest`, for constant-time comparison
+    pub fn root(&self) -> Option<Digest> {
+        self.root_hash().as_deref().and_then(Digest::from_slice)
+    }
+
+    /// Generate a proof that the leaf at `index` is part of the range
+    pub fn generate_proof(&self, index: usize) -> Option<MmrProof> {
+        if index >= self.leaf_count {
+            return None;
+        }
+
+        let mountains = mountains(self.leaf_count);
+        let (start, height) = mountains
+            .iter()
+            .copied()
+            .find(|&(start, height)| index < start + (1 << height))?;
+
+        let siblings = (0..height)
+            .map(|level| self.subtree(((index >> level) ^ 1) << level, level).to_vec())
+            .collect();
+        let peaks = mountains
+            .iter()
+            .filter(|&&(other, _)| other != start)
+            .map(|&(start, height)| self.subtree(start, height).to_vec())
+            .collect();
+
+        Some(MmrProof { leaf_index: index, leaf_count: self.leaf_count, siblings, peaks })
+    }
+
+    /// Verify a proof from `generate_proof` against an expected root
+    pub fn verify_proof(&self, leaf: &[u8], proof: &MmrProof, expected_root: &[u8]) -> bool {
+        if proof.leaf_index >= proof.leaf_count {
+            return false;
+        }
+        let mountains = mountains(proof.leaf_count);
+        let Some(position) = mountains
+            .iter()
+            .position(|&(start, height)| proof.leaf_index < start + (1 << height))
+        else {
+            return false;
+        };
+        if proof.siblings.len() != mountains[position].1
+            || proof.peaks.len() + 1 != mountains.len()
+        {
+            return false;
+        }
+
+        let mut hash = self.hasher.hash(&[&[LEAF_PREFIX], leaf].concat());
+        for (level, sibling) in proof.siblings.iter().enumerate() {
+            hash = if (proof.leaf_index >> level) & 1 == 0 {
+                self.hash_node(&hash, sibling)
+            } else {
+                self.hash_node(sibling, &hash)
+            };
+        }
+
+        let mut peaks: Vec<&[u8]> = proof.peaks.iter().map(Vec::as_slice).collect();
+        peaks.insert(position, &hash);
+        self.bag(peaks).is_some_and(|root| constant_time_eq(&root, expected_root))
+    }
+
+    /// Generate a proof that the range at `old_size` leaves is a prefix of this one
+    ///
+    /// Returns `None` if `old_size` is 0 or larger than the range.
+    pub fn consistency_proof(&self, old_size: usize) -> Option<MmrConsistencyProof> {
+        if old_size == 0 || old_size > self.leaf_count {
+            return None;
+        }
+
+        let old_peaks = self.peaks(old_size).into_iter().map(<[u8]>::to_vec).collect();
+        let mut nodes = Vec::new();
+        for (start, height) in mountains(self.leaf_count) {
+            self.cover(start, height, old_size, &mut nodes);
+        }
+
+        Some(MmrConsistencyProof { old_peaks, nodes })
+    }
+
+    /// Collect the hashes of the subtrees of only new leaves under a node
+    fn cover(&self, start: usize, height: usize, old_size: usize, nodes: &mut Vec<Vec<u8>>) {
+        let end = start + (1 << height);
+        if start >= old_size {
+            nodes.push(self.subtree(start, height).to_vec());
+        } else if end > old_size {
+            self.cover(start, height - 1, old_size, nodes);
+            self.cover(start + (1 << (height - 1)), height - 1, old_size, nodes);
+        }
+        // Otherwise the node is one of the old peaks, which the verifier has
+    }
+
+    /// Verify a proof from `consistency_proof`
+    ///
+    /// Checks that the range with root `new_root` and `new_size` leaves
+    /// starts with the `old_size` leaves of the range with root `old_root`.
+    pub fn verify_consistency(
+        &self,
+        old_root: &[u8],
+        old_size: usize,
+        new_root: &[u8],
+        new_size: usize,
+        proof: &MmrConsistencyProof,
+    ) -> bool {
+        if old_size == 0
+            || old_size > new_size
+            || proof.old_peaks.len() != old_size.count_ones() as usize
+        {
+            return false;
+        }
+        let old_peaks: Vec<&[u8]> = proof.old_peaks.iter().map(Vec::as_slice).collect();
+        if !self.bag(old_peaks).is_some_and(|root| constant_time_eq(&root, old_root)) {
+            return false;
+        }
+
+        let mut old_peaks = proof.old_peaks.iter();
+        let mut nodes = proof.nodes.iter();
+        let mut new_peaks = Vec::new();
+        for (start, height) in mountains(new_size) {
+            match self.rebuild(start, height, old_size, &mut old_peaks, &mut nodes) {
+                Some(peak) => new_peaks.push(peak),
+                None => return false,
+            }
+        }
+
+        // Every old peak and node must have been used
+        old_peaks.next().is_none()
+            && nodes.next().is_none()
+            && self
+                .bag(new_peaks.iter().map(Vec::as_slice).collect())
+                .is_some_and(|root| constant_time_eq(&root, new_root))
+    }
+
+    /// Recompute a node from old peaks and new subtree roots, mirroring `cover`
+    fn rebuild<'a>(
+        &self,
+        start: usize,
+        height: usize,
+        old_size: usize,
+        old_peaks: &mut impl Iterator<Item = &'a Vec<u8>>,
+        nodes: &mut impl Iterator<Item = &'a Vec<u8>>,
+    ) -> Option<Vec<u8>> {
+        let end = start + (1 << height);
+        if start >= old_size {
+            nodes.next().cloned()
+        } else if end > old_size {
+            let left = self.rebuild(start, height - 1, old_size, old_peaks, nodes)?;
+            let right =
+                self.rebuild(start + (1 << (height - 1)), height - 1, old_size, old_peaks, nodes)?;
+            Some(self.hash_node(&left, &right))
+        } else {
+            old_peaks.next().cloned()
+        }
+    }
+
+    /// The peaks of the range when it had `leaf_count` leaves, left to right
+    fn peaks(&self, leaf_count: usize) -> Vec<&[u8]> {
+        mountains(leaf_count)
+            .into_iter()
+            .map(|(start, height)| self.subtree(start, height))
+            .collect()
+    }
+
+    /// The root of the perfect subtree of height `height` starting at leaf `start`
+    fn subtree(&self, start: usize, height: usize) -> &[u8] {
+        &self.nodes[leaf_position(start) + (2 << height) - 2]
+    }
+
+    fn bag(&self, peaks: Vec<&[u8]>) -> Option<Vec<u8>> {
+        let (last, rest) = peaks.split_last()?;
+        Some(rest.iter().rev().fold(last.to_vec(), |acc, peak| self.hash_node(peak, &acc)))
+    }
+
+    fn hash_node(&self, left: &[u8], right: &[u8]) -> Vec<u8> {
+        self.hasher.hash(&[&[NODE_PREFIX], left, right].concat())
+    }
+}
+
+/// The `(first leaf, height)` of each mountain for `leaf_count` leaves, left to right
+fn mountains(leaf_count: usize) -> Vec<(usize, usize)> {
+    let mut start = 0;
+    (0..usize::BITS as usize)
+        .rev()
+        .filter(|&height| leaf_count & (1 << height) != 0)
+        .map(|height| {
+            let mountain = (start, height);
+            start += 1 << height;
+            mountain
+        })
+        .collect()
+}
+
+/// The post-order position of leaf `index` among all nodes
+fn leaf_position(index: usize) -> usize {
+    2 * index - index.count_ones() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::{MerkleTree, TreeMode};
+    use crate::sha256::Sha256Hasher;
+    use alloc::format;
+    use alloc::vec;
+
+    fn records(count: usize) -> Vec<Vec<u8>> {
+        (0..count).map(|i| format!("scan root {}", i).into_bytes()).collect()
+    }
+
+    #[test]
+    fn test_mmr_mountains_match_merkle_trees() {
+        let records = records(11);
+        let mut mmr = MerkleMountainRange::new(Sha256Hasher);
+        for record in &records {
+            mmr.push(record);
+        }
+        assert_eq!(mmr.leaf_count(), 11);
+        assert_eq!(mmr.nodes.len(), 2 * 11 - 3);
+
+        // 11 leaves make mountains of 8, 2 and 1 leaves, each an RFC 6962 tree
+        let peaks: Vec<Vec<u8>> = [(0, 8), (8, 10), (10, 11)]
+            .iter()
+            .map(|&(start, end)| {
+                let mut tree = MerkleTree::new(Sha256Hasher).with_mode(TreeMode::Rfc6962);
+                tree.build(records[start..end].iter().map(Vec::as_slice).collect());
+                tree.root_hash().unwrap().to_vec()
+            })
+            .collect();
+        assert_eq!(mmr.peaks(11), peaks.iter().map(Vec::as_slice).collect::<Vec<_>>());
+
+        let inner = mmr.hash_node(&peaks[1], &peaks[2]);
+        assert_eq!(mmr.root_hash(), Some(mmr.hash_node(&peaks[0], &inner)));
+        assert!(MerkleMountainRange::new(Sha256Hasher).root_hash().is_none());
+    }
+
+    #[test]
+    fn test_mmr_inclusion_proofs() {
+        let records = records(20);
+        let mut mmr = MerkleMountainRange::new(Sha256Hasher);
+
+        for (count, record) in records.iter().enumerate() {
+            assert_eq!(mmr.push(record), count);
+            let root = mmr.root_hash().unwrap();
+
+            for (index, record) in records[..=count].iter().enumerate() {
+                let proof = mmr.generate_proof(index).unwrap();
+                assert!(mmr.verify_proof(record, &proof, &root), "{} of {}", index, count + 1);
+                assert!(!mmr.verify_proof(b"forged", &proof, &root));
+
+                let moved = MmrProof { leaf_index: index ^ 1, ..proof };
+                assert!(!mmr.verify_proof(record, &moved, &root));
+            }
+            assert!(mmr.generate_proof(count + 1).is_none());
+        }
+    }
+
+    #[test]
+    fn test_mmr_consistency_proofs() {
+        let records = records(20);
+        let mut mmr = MerkleMountainRange::new(Sha256Hasher);
+        let mut roots = vec![Vec::new()];
+        for record in &records {
+            mmr.push(record);
+            roots.push(mmr.root_hash().unwrap());
+        }
+
+        for new_size in 1..=20 {
+            let mut grown = MerkleMountainRange::new(Sha256Hasher);
+            for record in &records[..new_size] {
+                grown.push(record);
+            }
+
+            for old_size in 1..=new_size {
+                let proof = grown.consistency_proof(old_size).unwrap();
+                let (old_root, new_root) = (&roots[old_size], &roots[new_size]);
+                assert!(
+                    grown.verify_consistency(old_root, old_size, new_root, new_size, &proof),
+                    "{} -> {}",
+                    old_size,
+                    new_size
+                );
+
+                let forged = &roots[old_size % 20 + 1];
+                assert!(!grown.verify_consistency(forged, old_size, new_root, new_size, &proof));
+            }
+        }
+
+        // A history with a rewritten record is not an extension
+        let mut rewritten = MerkleMountainRange::new(Sha256Hasher);
+        for (i, record) in records.iter().enumerate() {
+            rewritten.push(if i == 3 { b"tampered" } else { record });
+        }
+        let proof = rewritten.consistency_proof(5).unwrap();
+        let new_root = rewritten.root_hash().unwrap();
+        assert!(!rewritten.verify_consistency(&roots[5], 5, &new_root, 20, &proof));
+
+        assert!(mmr.consistency_proof(0).is_none());
+        assert!(mmr.consistency_proof(21).is_none());
+    }
+}
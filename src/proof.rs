@@ -0,0 +1,760 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::algorithm::HashAlgorithm;
+use crate::merkle::{HashFunction, MerkleTree, OddNodePolicy, PairOrder, TreeMode};
+use crate::utility::{bytes_to_hex, constant_time_eq, hex_to_bytes};
+
+/// First bytes of every binary proof
+const MAGIC: &[u8; 4] = b"FSGP";
+
+/// Version of the binary and JSON encodings written by this crate
+pub const VERSION: u8 = 1;
+
+/// Stable ids and names of each setting in the encodings
+const ALGORITHMS: [(HashAlgorithm, u8); 12] = [
+    (HashAlgorithm::Sha256, 1),
+    (HashAlgorithm::Sha224, 2),
+    (HashAlgorithm::Sha384, 3),
+    (HashAlgorithm::Sha512, 4),
+    (HashAlgorithm::Sha512_256, 5),
+    (HashAlgorithm::Sha3_224, 6),
+    (HashAlgorithm::Sha3_256, 7),
+    (HashAlgorithm::Sha3_384, 8),
+    (HashAlgorithm::Sha3_512, 9),
+    (HashAlgorithm::Shake128, 10),
+    (HashAlgorithm::Shake256, 11),
+    (HashAlgorithm::Blake3, 12),
+];
+const MODES: [(TreeMode, u8, &str); 2] =
+    [(TreeMode::Legacy, 0, "legacy"), (TreeMode::Rfc6962, 1, "rfc6962")];
+const PAIR_ORDERS: [(PairOrder, u8, &str); 2] =
+    [(PairOrder::Positional, 0, "positional"), (PairOrder::Sorted, 1, "sorted")];
+const ODD_NODE_POLICIES: [(OddNodePolicy, u8, &str); 2] =
+    [(OddNodePolicy::Duplicate, 0, "duplicate"), (OddNodePolicy::Promote, 1, "promote")];
+
+/// Inclusion proof for one leaf, with everything needed to check it
+///
+/// `MerkleTree::generate_proof` only returns the sibling hashes. This also
+/// records the hash algorithm, the leaf's position, the size of the tree,
+/// how the tree hashes and orders nodes and the root the proof leads to, so a
+/// proof can be saved and handed to someone who never saw the tree.
+///
+/// The binary form, from `to_bytes`, is
+///
+/// ```text
+/// "FSGP" version algorithm mode pair-order odd-node-policy   (one byte each)
+/// leaf-index leaf-count                                       (LEB128)
+/// root                                                        (output_len() bytes)
+/// sibling-count                                               (LEB128)
+/// siblings                                                    (output_len() bytes each)
+/// ```
+///
+/// and the JSON form, from `to_json`, has the same fields with names for the
+/// algorithm and settings and hex hashes:
+///
+/// ```text
+/// {"version":1,"algorithm":"sha256","mode":"rfc6962","pair_order":"positional",
+///  "odd_node_policy":"promote","leaf_index":2,"leaf_count":5,"root":"…",
+///  "siblings":["…"]}
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub algorithm: HashAlgorithm,
+    pub mode: TreeMode,
+    pub pair_order: PairOrder,
+    pub odd_node_policy: OddNodePolicy,
+    /// Position of the leaf
+    pub leaf_index: usize,
+    /// Number of leaves in the tree
+    pub leaf_count: usize,
+    /// Root of the tree the proof was generated from
+    pub root: Vec<u8>,
+    /// Sibling hashes from the leaf up, as returned by `generate_proof`
+    pub siblings: Vec<Vec<u8>>,
+}
+
+/// Error returned when a proof cannot be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseProofError {
+    /// Not a proof: wrong magic bytes, malformed JSON, or a missing, repeated
+    /// or unknown field
+    InvalidFormat,
+    /// Written with a version of the encoding this crate does not know
+    UnsupportedVersion(u64),
+    /// A hash algorithm this crate does not know
+    UnknownAlgorithm,
+    /// A tree mode, pair order or odd-node policy this crate does not know
+    UnknownScheme,
+    /// A root or sibling that is not hex, or not as long as the algorithm's digests
+    InvalidDigest,
+    /// The input ends in the middle of the proof
+    Truncated,
+    /// More input after the end of the proof
+    TrailingData,
+}
+
+impl fmt::Display for ParseProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseProofError::InvalidFormat => f.write_str("malformed proof"),
+            ParseProofError::UnsupportedVersion(version) => {
+                write!(f, "unsupported proof version {}", version)
+            }
+            ParseProofError::UnknownAlgorithm => f.write_str("unknown hash algorithm in proof"),
+            ParseProofError::UnknownScheme => f.write_str("unknown tree scheme in proof"),
+            ParseProofError::InvalidDigest => f.write_str("invalid hash in proof"),
+            ParseProofError::Truncated => f.write_str("proof is truncated"),
+            ParseProofError::TrailingData => f.write_str("unexpected data after proof"),
+        }
+    }
+}
+
+impl core::error::Error for ParseProofError {}
+
+/// Reason a `MerkleProof` failed to verify
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofError {
+    /// The proof was made with a different hash algorithm than the hasher's
+    WrongAlgorithm { proof: HashAlgorithm, hasher: HashAlgorithm },
+    /// The leaf index is not below the tree size
+    IndexOutOfRange { index: usize, leaf_count: usize },
+    /// The proof does not have one sibling per level the leaf has a sibling at
+    LengthMismatch { expected: usize, actual: usize },
+    /// A sibling or a root is not as long as the proof's algorithm's digests
+    DigestLength { expected: usize, actual: usize },
+    /// The proof is well formed but leads to a different root, or claims one
+    RootMismatch,
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofError::WrongAlgorithm { proof, hasher } => {
+                write!(f, "proof uses {} but the hasher is {}", proof, hasher)
+            }
+            ProofError::IndexOutOfRange { index, leaf_count } => {
+                write!(f, "leaf index {} is out of range for {} leaves", index, leaf_count)
+            }
+            ProofError::LengthMismatch { expected, actual } => {
+                write!(f, "proof has {} sibling hashes, expected {}", actual, expected)
+            }
+            ProofError::DigestLength { expected, actual } => {
+                write!(f, "hash is {} bytes long, expected {}", actual, expected)
+            }
+            ProofError::RootMismatch => f.write_str("proof does not lead to the expected root"),
+        }
+    }
+}
+
+impl core::error::Error for ProofError {}
+
+/// Verify a proof that `leaf` is part of the tree with root `root`
+///
+/// Needs no tree: the proof says how the tree was hashed, and `hasher` only
+/// has to compute its algorithm. Hashers that cannot tell their algorithm,
+/// such as `KeyedHasher`, skip that check.
+///
+/// `root` is the root the verifier trusts, e.g. from a manifest; the proof's
+/// own `root` must match it. Passing `&proof.root` only checks that the
+/// proof is consistent with the root it carries.
+pub fn verify_inclusion<H: HashFunction>(
+    hasher: &H,
+    leaf: &[u8],
+    proof: &MerkleProof,
+    root: &[u8],
+) -> Result<(), ProofError> {
+    if let Some(algorithm) = hasher.algorithm().filter(|&algorithm| algorithm != proof.algorithm) {
+        return Err(ProofError::WrongAlgorithm { proof: proof.algorithm, hasher: algorithm });
+    }
+    if proof.leaf_index >= proof.leaf_count {
+        return Err(ProofError::IndexOutOfRange {
+            index: proof.leaf_index,
+            leaf_count: proof.leaf_count,
+        });
+    }
+
+    // The tree is never built; it only carries the hasher and settings
+    let tree = MerkleTree::new(hasher)
+        .with_mode(proof.mode)
+        .with_pair_order(proof.pair_order)
+        .with_odd_node_policy(proof.odd_node_policy);
+
+    let expected = tree.proof_len(proof.leaf_index, proof.leaf_count);
+    if proof.siblings.len() != expected {
+        return Err(ProofError::LengthMismatch { expected, actual: proof.siblings.len() });
+    }
+    let digest_len = proof.algorithm.output_len();
+    for hash in proof.siblings.iter().map(Vec::as_slice).chain([proof.root.as_slice(), root]) {
+        if hash.len() != digest_len {
+            return Err(ProofError::DigestLength { expected: digest_len, actual: hash.len() });
+        }
+    }
+
+    if !constant_time_eq(&proof.root, root)
+        || !tree.verify_proof(leaf, proof.leaf_index, proof.leaf_count, &proof.siblings, root)
+    {
+        return Err(ProofError::RootMismatch);
+    }
+    Ok(())
+}
+
+impl<H: HashFunction> MerkleTree<H> {
+    /// Generate a self-describing proof for a given leaf index
+    ///
+    /// Like `generate_proof`, but records the tree's algorithm, settings and
+    /// root with the siblings so the proof can be serialized. Returns `None`
+    /// if the index is out of range or the hasher is not one of
+    /// `HashAlgorithm`, such as a `KeyedHasher`.
+    pub fn generate_merkle_proof(&self, index: usize) -> Option<MerkleProof> {
+        Some(MerkleProof {
+            algorithm: self.hasher().algorithm()?,
+            mode: self.mode(),
+            pair_order: self.pair_order(),
+            odd_node_policy: self.odd_node_policy(),
+            leaf_index: index,
+            leaf_count: self.leaf_count(),
+            root: self.root_hash()?.to_vec(),
+            siblings: self.generate_proof(index)?,
+        })
+    }
+}
+
+impl MerkleProof {
+    /// Encode the proof in the compact binary form
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let hashes_len = self.root.len() + self.siblings.iter().map(Vec::len).sum::<usize>();
+        let mut bytes = Vec::with_capacity(32 + hashes_len);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(algorithm_id(self.algorithm));
+        bytes.push(setting(&MODES, self.mode).0);
+        bytes.push(setting(&PAIR_ORDERS, self.pair_order).0);
+        bytes.push(setting(&ODD_NODE_POLICIES, self.odd_node_policy).0);
+        write_varint(&mut bytes, self.leaf_index as u64);
+        write_varint(&mut bytes, self.leaf_count as u64);
+        bytes.extend_from_slice(&self.root);
+        write_varint(&mut bytes, self.siblings.len() as u64);
+        for sibling in &self.siblings {
+            bytes.extend_from_slice(sibling);
+        }
+        bytes
+    }
+
+    /// Decode a proof written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseProofError> {
+        let mut input = bytes.strip_prefix(MAGIC).ok_or(ParseProofError::InvalidFormat)?;
+
+        let version = read_byte(&mut input)?;
+        if version != VERSION {
+            return Err(ParseProofError::UnsupportedVersion(version.into()));
+        }
+        let algorithm = algorithm_by_id(read_byte(&mut input)?)?;
+        let mode = setting_by_id(&MODES, read_byte(&mut input)?)?;
+        let pair_order = setting_by_id(&PAIR_ORDERS, read_byte(&mut input)?)?;
+        let odd_node_policy = setting_by_id(&ODD_NODE_POLICIES, read_byte(&mut input)?)?;
+        let leaf_index = read_varint(&mut input)?;
+        let leaf_count = read_varint(&mut input)?;
+        let digest_len = algorithm.output_len();
+        let root = read_bytes(&mut input, digest_len)?.to_vec();
+        let sibling_count = read_varint(&mut input)?;
+
+        // Check the length before allocating anything for the siblings
+        match sibling_count.checked_mul(digest_len) {
+            Some(len) if len == input.len() => {}
+            Some(len) if len > input.len() => return Err(ParseProofError::Truncated),
+            Some(_) => return Err(ParseProofError::TrailingData),
+            None => return Err(ParseProofError::Truncated),
+        }
+        let siblings = input.chunks_exact(digest_len).map(<[u8]>::to_vec).collect();
+
+        Ok(MerkleProof {
+            algorithm,
+            mode,
+            pair_order,
+            odd_node_policy,
+            leaf_index,
+            leaf_count,
+            root,
+            siblings,
+        })
+    }
+
+    /// Encode the proof as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        let siblings: Vec<String> =
+            self.siblings.iter().map(|sibling| format!("\"{}\"", bytes_to_hex(sibling))).collect();
+        format!(
+            "{{\"version\":{},\"algorithm\":\"{}\",\"mode\":\"{}\",\"pair_order\":\"{}\",\
+             \"odd_node_policy\":\"{}\",\"leaf_index\":{},\"leaf_count\":{},\"root\":\"{}\",\
+             \"siblings\":[{}]}}",
+            VERSION,
+            self.algorithm.name(),
+            setting(&MODES, self.mode).1,
+            setting(&PAIR_ORDERS, self.pair_order).1,
+            setting(&ODD_NODE_POLICIES, self.odd_node_policy).1,
+            self.leaf_index,
+            self.leaf_count,
+            bytes_to_hex(&self.root),
+            siblings.join(","),
+        )
+    }
+
+    /// Decode a proof written by `to_json`
+    ///
+    /// Fields may come in any order and with any whitespace between tokens,
+    /// but all of them are required and no others are allowed.
+    pub fn from_json(json: &str) -> Result<Self, ParseProofError> {
+        let mut parser = JsonParser { input: json.as_bytes(), pos: 0 };
+        let mut algorithm = None;
+        let mut mode = None;
+        let mut pair_order = None;
+        let mut odd_node_policy = None;
+        let mut leaf_index = None;
+        let mut leaf_count = None;
+        let mut root = None;
+        let mut siblings = None;
+        let mut version = None;
+
+        parser.expect(b'{')?;
+        loop {
+            let key = parser.string()?;
+            parser.expect(b':')?;
+            match key.as_str() {
+                "version" => {
+                    let value = parser.number()?;
+                    // Checked at once, so a newer proof is reported as such
+                    // rather than as having unknown fields
+                    if value != u64::from(VERSION) {
+                        return Err(ParseProofError::UnsupportedVersion(value));
+                    }
+                    set_once(&mut version, value)?
+                }
+                "algorithm" => {
+                    let name = parser.string()?;
+                    let value = name.parse().map_err(|_| ParseProofError::UnknownAlgorithm)?;
+                    set_once(&mut algorithm, value)?
+                }
+                "mode" => set_once(&mut mode, setting_by_name(&MODES, &parser.string()?)?)?,
+                "pair_order" => {
+                    set_once(&mut pair_order, setting_by_name(&PAIR_ORDERS, &parser.string()?)?)?
+                }
+                "odd_node_policy" => {
+                    let value = setting_by_name(&ODD_NODE_POLICIES, &parser.string()?)?;
+                    set_once(&mut odd_node_policy, value)?
+                }
+                "leaf_index" => set_once(&mut leaf_index, to_usize(parser.number()?)?)?,
+                "leaf_count" => set_once(&mut leaf_count, to_usize(parser.number()?)?)?,
+                "root" => set_once(&mut root, parser.string()?)?,
+                "siblings" => set_once(&mut siblings, parser.strings()?)?,
+                _ => return Err(ParseProofError::InvalidFormat),
+            }
+            if !parser.eat(b',') {
+                break;
+            }
+        }
+        parser.expect(b'}')?;
+        parser.end()?;
+
+        let missing = ParseProofError::InvalidFormat;
+        version.ok_or(missing)?;
+        let algorithm: HashAlgorithm = algorithm.ok_or(missing)?;
+        let parse_hash = |hex: &String| {
+            hex_to_bytes(hex)
+                .filter(|hash| hash.len() == algorithm.output_len())
+                .ok_or(ParseProofError::InvalidDigest)
+        };
+        let root = parse_hash(&root.ok_or(missing)?)?;
+        let siblings = siblings.ok_or(missing)?.iter().map(parse_hash).collect::<Result<_, _>>()?;
+
+        Ok(MerkleProof {
+            algorithm,
+            mode: mode.ok_or(missing)?,
+            pair_order: pair_order.ok_or(missing)?,
+            odd_node_policy: odd_node_policy.ok_or(missing)?,
+            leaf_index: leaf_index.ok_or(missing)?,
+            leaf_count: leaf_count.ok_or(missing)?,
+            root,
+            siblings,
+        })
+    }
+}
+
+fn algorithm_by_id(id: u8) -> Result<HashAlgorithm, ParseProofError> {
+    ALGORITHMS
+        .iter()
+        .find(|&&(_, known)| known == id)
+        .map(|&(algorithm, _)| algorithm)
+        .ok_or(ParseProofError::UnknownAlgorithm)
+}
+
+fn algorithm_id(algorithm: HashAlgorithm) -> u8 {
+    ALGORITHMS
+        .iter()
+        .find(|&&(known, _)| known == algorithm)
+        .map(|&(_, id)| id)
+        .expect("every algorithm has an id")
+}
+
+/// The id and name of a tree setting
+fn setting<T: PartialEq>(table: &[(T, u8, &'static str)], value: T) -> (u8, &'static str) {
+    table
+        .iter()
+        .find(|(known, _, _)| *known == value)
+        .map(|&(_, id, name)| (id, name))
+        .expect("every setting has an id and a name")
+}
+
+fn setting_by_id<T: Copy>(table: &[(T, u8, &str)], id: u8) -> Result<T, ParseProofError> {
+    table
+        .iter()
+        .find(|&&(_, known, _)| known == id)
+        .map(|&(value, _, _)| value)
+        .ok_or(ParseProofError::UnknownScheme)
+}
+
+fn setting_by_name<T: Copy>(table: &[(T, u8, &str)], name: &str) -> Result<T, ParseProofError> {
+    table
+        .iter()
+        .find(|&&(_, _, known)| known == name)
+        .map(|&(value, _, _)| value)
+        .ok_or(ParseProofError::UnknownScheme)
+}
+
+fn set_once<T>(field: &mut Option<T>, value: T) -> Result<(), ParseProofError> {
+    if field.replace(value).is_some() {
+        return Err(ParseProofError::InvalidFormat);
+    }
+    Ok(())
+}
+
+fn to_usize(value: u64) -> Result<usize, ParseProofError> {
+    usize::try_from(value).map_err(|_| ParseProofError::InvalidFormat)
+}
+
+/// Append `value` as unsigned LEB128: 7 bits per byte, low bits first
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_byte(input: &mut &[u8]) -> Result<u8, ParseProofError> {
+    let (&byte, rest) = input.split_first().ok_or(ParseProofError::Truncated)?;
+    *input = rest;
+    Ok(byte)
+}
+
+fn read_bytes<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], ParseProofError> {
+    if input.len() < len {
+        return Err(ParseProofError::Truncated);
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+fn read_varint(input: &mut &[u8]) -> Result<usize, ParseProofError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_byte(input)?;
+        let bits = u64::from(byte & 0x7f);
+        if bits << shift >> shift != bits {
+            return Err(ParseProofError::InvalidFormat);
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return to_usize(value);
+        }
+    }
+    Err(ParseProofError::InvalidFormat)
+}
+
+/// Just enough JSON for proofs: objects, strings, arrays of strings and
+/// unsigned integers
+struct JsonParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    /// The next byte after any whitespace
+    fn peek(&mut self) -> Result<u8, ParseProofError> {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.input.get(self.pos) {
+            self.pos += 1;
+        }
+        self.input.get(self.pos).copied().ok_or(ParseProofError::Truncated)
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Ok(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseProofError> {
+        self.peek()?;
+        if !self.eat(byte) {
+            return Err(ParseProofError::InvalidFormat);
+        }
+        Ok(())
+    }
+
+    fn end(&mut self) -> Result<(), ParseProofError> {
+        match self.peek() {
+            Err(_) => Ok(()),
+            Ok(_) => Err(ParseProofError::TrailingData),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseProofError> {
+        self.expect(b'"')?;
+        let mut value = Vec::new();
+        loop {
+            let byte = *self.input.get(self.pos).ok_or(ParseProofError::Truncated)?;
+            self.pos += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = *self.input.get(self.pos).ok_or(ParseProofError::Truncated)?;
+                    self.pos += 1;
+                    match escaped {
+                        b'"' | b'\\' | b'/' => value.push(escaped),
+                        _ => return Err(ParseProofError::InvalidFormat),
+                    }
+                }
+                0x00..=0x1f => return Err(ParseProofError::InvalidFormat),
+                _ => value.push(byte),
+            }
+        }
+        String::from_utf8(value).map_err(|_| ParseProofError::InvalidFormat)
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, ParseProofError> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+        if self.eat(b']') {
+            return Ok(values);
+        }
+        loop {
+            values.push(self.string()?);
+            if !self.eat(b',') {
+                break;
+            }
+        }
+        self.expect(b']')?;
+        Ok(values)
+    }
+
+    fn number(&mut self) -> Result<u64, ParseProofError> {
+        self.peek()?;
+        let start = self.pos;
+        let mut value = 0u64;
+        while let Some(&digit @ b'0'..=b'9') = self.input.get(self.pos) {
+            value = value
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(u64::from(digit - b'0')))
+                .ok_or(ParseProofError::InvalidFormat)?;
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(ParseProofError::InvalidFormat);
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blake3::Blake3Hasher;
+    use crate::hmac::KeyedHasher;
+    use crate::sha256::Sha256Hasher;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    const LEAVES: [&[u8]; 5] =
+        [b"etc/hosts", b"etc/passwd", b"etc/shadow", b"usr/bin/ls", b"usr/bin/sh"];
+
+    fn sample_tree() -> MerkleTree<HashAlgorithm> {
+        let mut tree = MerkleTree::new(HashAlgorithm::Sha256)
+            .with_mode(TreeMode::Rfc6962)
+            .with_odd_node_policy(OddNodePolicy::RFC6962_SPLIT);
+        tree.build(LEAVES.to_vec());
+        tree
+    }
+
+    #[test]
+    fn test_proof_binary_and_json_round_trip() {
+        let tree = sample_tree();
+        let proof = tree.generate_merkle_proof(2).unwrap();
+        assert_eq!(proof.siblings, tree.generate_proof(2).unwrap());
+        assert_eq!(proof.root, tree.root_hash().unwrap());
+        assert!(tree.generate_merkle_proof(5).is_none());
+
+        // Any hasher that names its algorithm gives the same proof
+        let mut typed = MerkleTree::new(Sha256Hasher)
+            .with_mode(TreeMode::Rfc6962)
+            .with_odd_node_policy(OddNodePolicy::RFC6962_SPLIT);
+        typed.build(LEAVES.to_vec());
+        assert_eq!(typed.generate_merkle_proof(2), Some(proof.clone()));
+        let mut keyed = MerkleTree::new(KeyedHasher::new(b"manifest key"));
+        keyed.build(LEAVES.to_vec());
+        assert!(keyed.generate_merkle_proof(2).is_none());
+
+        let bytes = proof.to_bytes();
+        assert_eq!(&bytes[..11], b"FSGP\x01\x01\x01\x00\x01\x02\x05");
+        assert_eq!(&bytes[11..43], proof.root.as_slice());
+        assert_eq!(bytes.len(), 4 + 5 + 2 + 32 + 1 + 32 * proof.siblings.len());
+        assert_eq!(MerkleProof::from_bytes(&bytes), Ok(proof.clone()));
+
+        let json = proof.to_json();
+        let root = bytes_to_hex(&proof.root);
+        assert!(json.starts_with(&format!(
+            "{{\"version\":1,\"algorithm\":\"sha256\",\"mode\":\"rfc6962\",\
+             \"pair_order\":\"positional\",\"odd_node_policy\":\"promote\",\
+             \"leaf_index\":2,\"leaf_count\":5,\"root\":\"{}\",\"siblings\":[\"",
+            root
+        )));
+        assert_eq!(MerkleProof::from_json(&json), Ok(proof.clone()));
+
+        // Whitespace and field order are free
+        let siblings: Vec<String> =
+            proof.siblings.iter().map(|s| format!("\"{}\"", bytes_to_hex(s))).collect();
+        let reordered = format!(
+            " {{ \"siblings\" : [ {} ],\n \"leaf_count\": 5, \"leaf_index\": 2, \
+             \"odd_node_policy\": \"promote\", \"pair_order\": \"positional\",\n \
+             \"mode\": \"rfc6962\", \"root\": \"{}\",\n \
+             \"algorithm\": \"sha256\", \"version\": 1 }}\n",
+            siblings.join(", "),
+            root
+        );
+        assert_eq!(MerkleProof::from_json(&reordered), Ok(proof.clone()));
+
+        // Large indices take several varint bytes
+        let large =
+            MerkleProof { leaf_index: 300_000, leaf_count: 1 << 40, siblings: vec![], ..proof };
+        assert_eq!(MerkleProof::from_bytes(&large.to_bytes()), Ok(large.clone()));
+        assert_eq!(MerkleProof::from_json(&large.to_json()), Ok(large));
+    }
+
+    #[test]
+    fn test_verify_inclusion() {
+        let tree = sample_tree();
+        let root = tree.root_hash().unwrap();
+
+        for (index, leaf) in LEAVES.iter().enumerate() {
+            let proof = tree.generate_merkle_proof(index).unwrap();
+            let received = MerkleProof::from_json(&proof.to_json()).unwrap();
+            assert_eq!(verify_inclusion(&HashAlgorithm::Sha256, leaf, &received, root), Ok(()));
+            assert_eq!(verify_inclusion(&Sha256Hasher, leaf, &received, root), Ok(()));
+            assert_eq!(
+                verify_inclusion(&Sha256Hasher, b"etc/sudoers", &received, root),
+                Err(ProofError::RootMismatch)
+            );
+        }
+
+        let proof = tree.generate_merkle_proof(1).unwrap();
+        let check = |proof: &MerkleProof| verify_inclusion(&Sha256Hasher, LEAVES[1], proof, root);
+        assert_eq!(
+            verify_inclusion(&Blake3Hasher, LEAVES[1], &proof, root),
+            Err(ProofError::WrongAlgorithm {
+                proof: HashAlgorithm::Sha256,
+                hasher: HashAlgorithm::Blake3
+            })
+        );
+        assert_eq!(
+            check(&MerkleProof { leaf_index: 5, ..proof.clone() }),
+            Err(ProofError::IndexOutOfRange { index: 5, leaf_count: 5 })
+        );
+        let mut short = proof.clone();
+        short.siblings.pop();
+        assert_eq!(check(&short), Err(ProofError::LengthMismatch { expected: 3, actual: 2 }));
+        let mut truncated = proof.clone();
+        truncated.siblings[0].truncate(20);
+        assert_eq!(check(&truncated), Err(ProofError::DigestLength { expected: 32, actual: 20 }));
+        assert_eq!(
+            verify_inclusion(&Sha256Hasher, LEAVES[1], &proof, &root[..16]),
+            Err(ProofError::DigestLength { expected: 32, actual: 16 })
+        );
+        let mut short_root = proof.clone();
+        short_root.root.truncate(16);
+        assert_eq!(check(&short_root), Err(ProofError::DigestLength { expected: 32, actual: 16 }));
+
+        // The proof carries the root it leads to, but must lead to the trusted one
+        assert_eq!(verify_inclusion(&Sha256Hasher, LEAVES[1], &proof, &proof.root), Ok(()));
+        let mut other = sample_tree();
+        other.update_leaf(4, b"usr/bin/evil");
+        let forged = other.generate_merkle_proof(1).unwrap();
+        assert_eq!(
+            verify_inclusion(&Sha256Hasher, LEAVES[1], &forged, &forged.root),
+            Ok(())
+        );
+        assert_eq!(check(&forged), Err(ProofError::RootMismatch));
+        let relabeled = MerkleProof { root: forged.root.clone(), ..proof.clone() };
+        assert_eq!(check(&relabeled), Err(ProofError::RootMismatch));
+
+        // The leaf moved to another position, or a tree hashed another way
+        let moved = MerkleProof { leaf_index: 0, ..proof.clone() };
+        assert_eq!(check(&moved), Err(ProofError::RootMismatch));
+        let legacy = MerkleProof { mode: TreeMode::Legacy, ..proof.clone() };
+        assert_eq!(check(&legacy), Err(ProofError::RootMismatch));
+
+        // A keyed hasher cannot be checked against the algorithm, only the root
+        let keyed = KeyedHasher::new(b"manifest key");
+        assert_eq!(
+            verify_inclusion(&keyed, LEAVES[1], &proof, root),
+            Err(ProofError::RootMismatch)
+        );
+    }
+
+    #[test]
+    fn test_proof_parse_errors() {
+        let proof = sample_tree().generate_merkle_proof(4).unwrap();
+        let bytes = proof.to_bytes();
+
+        assert_eq!(MerkleProof::from_bytes(b"GARBAGE"), Err(ParseProofError::InvalidFormat));
+        let mut newer = bytes.clone();
+        newer[4] = 2;
+        assert_eq!(MerkleProof::from_bytes(&newer), Err(ParseProofError::UnsupportedVersion(2)));
+        let mut unknown = bytes.clone();
+        unknown[5] = 0xee;
+        assert_eq!(MerkleProof::from_bytes(&unknown), Err(ParseProofError::UnknownAlgorithm));
+        unknown[5] = 1;
+        unknown[7] = 9;
+        assert_eq!(MerkleProof::from_bytes(&unknown), Err(ParseProofError::UnknownScheme));
+        for len in 4..bytes.len() {
+            assert_eq!(MerkleProof::from_bytes(&bytes[..len]), Err(ParseProofError::Truncated));
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(MerkleProof::from_bytes(&trailing), Err(ParseProofError::TrailingData));
+
+        let json = proof.to_json();
+        let sibling = bytes_to_hex(&proof.siblings[0]);
+        let root = bytes_to_hex(&proof.root);
+        let index = "\"leaf_index\":4";
+        let cases = [
+            (json.replacen(":1,", ":7,", 1), ParseProofError::UnsupportedVersion(7)),
+            (json.replace("sha256", "md5"), ParseProofError::UnknownAlgorithm),
+            (json.replace("positional", "random"), ParseProofError::UnknownScheme),
+            (json.replace(&sibling, &sibling[2..]), ParseProofError::InvalidDigest),
+            (json.replace(&sibling, &"z".repeat(64)), ParseProofError::InvalidDigest),
+            (json.replace(&root, &root[2..]), ParseProofError::InvalidDigest),
+            (json.replace(&format!("{},", index), ""), ParseProofError::InvalidFormat),
+            (json.replace(index, &format!("{},{}", index, index)), ParseProofError::InvalidFormat),
+            (json.replace("\"mode\"", "\"depth\":3,\"mode\""), ParseProofError::InvalidFormat),
+            (json[..json.len() - 2].to_string(), ParseProofError::Truncated),
+            (format!("{}{{}}", json), ParseProofError::TrailingData),
+        ];
+        for (json, error) in cases {
+            assert_eq!(MerkleProof::from_json(&json), Err(error), "{}", json);
+        }
+    }
+}
@@ -1,5 +1,8 @@
-#[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::*;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+
 /// Computes the SHA-256 hash of the input data.
 /// 
 /// This function takes a byte slice as input, applies the SHA-256 hashing algorithm,
@@ -13,59 +16,501 @@ use std::arch::x86_64::*;
 ///
 /// A 32-byte array containing the SHA-256 hash of the input data.
 pub fn sha256(input: &[u8]) -> [u8; 32] {
-    // Initial hash values as defined in the SHA-256 specification
-    let mut h: [u32; 8] = [
-        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
-        0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
-    ];
+    let mut hasher = Sha256::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Computes the SHA-256 hash of the input data at compile time.
+///
+/// This lets expected digests be computed in `const` items instead of
+/// pasting hex strings, e.g. `const DIGEST: [u8; 32] = sha256_const(include_bytes!("policy.toml"));`.
+/// It uses the portable compression function and no heap allocation; at
+/// runtime `sha256` is faster.
+///
+/// # Arguments
+///
+/// * `input` - A byte slice containing the data to be hashed.
+///
+/// # Returns
+///
+/// A 32-byte array containing the SHA-256 hash of the input data.
+pub const fn sha256_const(input: &[u8]) -> [u8; 32] {
+    let mut state = H0;
+
+    // Process each full 512-bit block straight from the input
+    let mut rest = input;
+    while rest.len() >= 64 {
+        let (block, tail) = rest.split_at(64);
+        compress(&mut state, block);
+        rest = tail;
+    }
 
-    // Pad the input message to prepare it for processing
-    let padded = pad_message(input);
+    // Pad the remaining bytes into one or two final blocks
+    let mut last = [0u8; 128];
+    let mut i = 0;
+    while i < rest.len() {
+        last[i] = rest[i];
+        i += 1;
+    }
+    last[rest.len()] = 0x80;
+
+    let last_len = if rest.len() >= 56 { 128 } else { 64 };
+    let bit_length = (input.len() as u64).wrapping_mul(8).to_be_bytes();
+    let mut i = 0;
+    while i < 8 {
+        last[last_len - 8 + i] = bit_length[i];
+        i += 1;
+    }
 
-    // Process each 512-bit block of the padded message
-    for block in padded.chunks(64) {
-            compress(&mut h, block);
+    let (first, second) = last.split_at(64);
+    compress(&mut state, first);
+    if last_len == 128 {
+        compress(&mut state, second);
     }
 
     // Convert the final hash values into a byte array
     let mut hash = [0u8; 32];
-    for (i, &val) in h.iter().enumerate() {
-        hash[i * 4..(i + 1) * 4].copy_from_slice(&val.to_be_bytes());
+    let mut i = 0;
+    while i < 32 {
+        hash[i] = state[i / 4].to_be_bytes()[i % 4];
+        i += 1;
     }
 
     hash
 }
 
-/// Pads the input message according to the SHA-256 specification.
+/// Computes the SHA-256 hash of the input data using only the portable
+/// implementation, regardless of what the CPU supports.
+///
+/// Mostly useful as a baseline for benchmarks and for cross-checking the
+/// hardware-accelerated backends.
+pub fn portable_sha256(input: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::with_backend(Backend::Portable);
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Computes the SHA-256 hash of the input data using the x86 SHA extensions.
+///
+/// # Returns
+///
+/// The 32-byte hash, or `None` if the CPU does not support the SHA extensions.
+pub fn simd_sha256(input: &[u8]) -> Option<[u8; 32]> {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if shani::is_supported() {
+            let mut hasher = Sha256::with_backend(Backend::ShaNi);
+            hasher.update(input);
+            return Some(hasher.finalize());
+        }
+    }
+    let _ = input;
+    None
+}
+
+/// Computes the SHA-256 hashes of many independent messages.
 ///
-/// The padding ensures that the message length is congruent to 448 modulo 512,
-/// followed by appending the original message length as a 64-bit integer.
+/// With AVX2 available, messages are hashed 8 at a time, one per vector lane;
+/// otherwise each message goes through `sha256` in turn. The output is the
+/// same either way.
 ///
 /// # Arguments
 ///
-/// * `message` - A byte slice containing the original message.
+/// * `inputs` - The messages to hash. They may have different lengths.
 ///
 /// # Returns
 ///
-/// A `Vec<u8>` containing the padded message.
-fn pad_message(message: &[u8]) -> Vec<u8> {
-    let mut padded = message.to_vec();
-    
-    // Step 1: Append a single '1' bit (0x80 in hexadecimal)
-    padded.push(0x80);
-    
-    // Step 2: Append '0' bits until the length is 448 modulo 512
-    while (padded.len() * 8) % 512 != 448 {
-        padded.push(0);
-    }
-    
-    // Step 3: Append the original message length as a 64-bit big-endian integer
-    let bit_length = (message.len() * 8) as u64;
-    padded.extend_from_slice(&bit_length.to_be_bytes());
-    
-    padded
+/// A `Vec` with the hash of each input, in the same order.
+#[cfg(feature = "alloc")]
+pub fn sha256_many(inputs: &[&[u8]]) -> Vec<[u8; 32]> {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if avx2::is_supported() {
+            let mut hashes = alloc::vec![[0u8; 32]; inputs.len()];
+            for (messages, out) in inputs.chunks(avx2::LANES).zip(hashes.chunks_mut(avx2::LANES)) {
+                // SAFETY: AVX2 support was checked above.
+                unsafe { avx2::hash_lanes(messages, out) };
+            }
+            return hashes;
+        }
+    }
+    inputs.iter().map(|input| sha256(input)).collect()
 }
 
+/// SHA-256 adapter for `merkle::HashFunction`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Hasher;
+
+#[cfg(feature = "alloc")]
+impl crate::merkle::HashFunction for Sha256Hasher {
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        sha256(input).to_vec()
+    }
+
+    fn hash_many(&self, inputs: &[&[u8]]) -> Vec<Vec<u8>> {
+        sha256_many(inputs).into_iter().map(|hash| hash.to_vec()).collect()
+    }
+
+    fn algorithm(&self) -> Option<crate::algorithm::HashAlgorithm> {
+        Some(crate::algorithm::HashAlgorithm::Sha256)
+    }
+}
+
+/// Computes the SHA-256 hash of everything read from `reader`.
+///
+/// The data is streamed through a fixed-size buffer, so files, pipes and
+/// sockets of any size are hashed without holding their content in memory.
+///
+/// # Arguments
+///
+/// * `reader` - The source to read until end of file.
+///
+/// # Returns
+///
+/// A 32-byte array containing the SHA-256 hash, or the first read error.
+#[cfg(feature = "std")]
+pub fn hash_reader<R: io::Read>(mut reader: R) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finalize())
+}
+
+/// Computes the SHA-224 hash of the input data.
+///
+/// SHA-224 is SHA-256 with different initial hash values, truncated to the
+/// first 28 bytes.
+///
+/// # Arguments
+///
+/// * `input` - A byte slice containing the data to be hashed.
+///
+/// # Returns
+///
+/// A 28-byte array containing the SHA-224 hash of the input data.
+pub fn sha224(input: &[u8]) -> [u8; 28] {
+    let mut hasher = Sha224::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// SHA-224 adapter for `merkle::HashFunction`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha224Hasher;
+
+#[cfg(feature = "alloc")]
+impl crate::merkle::HashFunction for Sha224Hasher {
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        sha224(input).to_vec()
+    }
+
+    fn algorithm(&self) -> Option<crate::algorithm::HashAlgorithm> {
+        Some(crate::algorithm::HashAlgorithm::Sha224)
+    }
+}
+
+/// Compression function implementation used by a hasher.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Backend {
+    Portable,
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    ShaNi,
+}
+
+impl Backend {
+    /// Picks the fastest backend supported by the running CPU.
+    fn detect() -> Self {
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        {
+            if shani::is_supported() {
+                return Backend::ShaNi;
+            }
+        }
+        Backend::Portable
+    }
+
+    /// Compresses a run of whole 64-byte blocks into the hash state.
+    fn compress_blocks(self, hash_state: &mut [u32; 8], blocks: &[u8]) {
+        debug_assert!(blocks.len().is_multiple_of(64));
+        match self {
+            Backend::Portable => {
+                for block in blocks.chunks_exact(64) {
+                    compress(hash_state, block);
+                }
+            }
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            // SAFETY: `ShaNi` is only ever selected after `shani::is_supported()`.
+            Backend::ShaNi => unsafe { shani::compress_blocks(hash_state, blocks) },
+        }
+    }
+}
+
+/// Initial hash values as defined in the SHA-256 specification
+const H0: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Initial hash values as defined in the SHA-224 specification
+const H0_224: [u32; 8] = [
+    0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939,
+    0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+];
+
+/// Size of a serialized `Sha256` midstate: state words, length and block buffer
+pub const STATE_LEN: usize = 32 + 8 + 64;
+
+/// Streaming SHA-256 hasher.
+///
+/// Data can be fed in arbitrarily sized pieces with `update`; full 512-bit
+/// blocks are compressed as soon as they are available and only the partial
+/// tail is buffered, so hashing a large input never requires holding it in
+/// memory.
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    buffer: [u8; 64],
+    buffer_len: usize,
+    length: u64,
+    backend: Backend,
+}
+
+impl Sha256 {
+    /// Creates a hasher with the initial SHA-256 state.
+    ///
+    /// The compression backend is chosen at runtime: the x86 SHA extensions
+    /// when the CPU has them, the portable implementation otherwise.
+    pub fn new() -> Self {
+        Self::with_backend(Backend::detect())
+    }
+
+    fn with_backend(backend: Backend) -> Self {
+        Self::with_iv(H0, backend)
+    }
+
+    fn with_iv(iv: [u32; 8], backend: Backend) -> Self {
+        Sha256 {
+            state: iv,
+            buffer: [0u8; 64],
+            buffer_len: 0,
+            length: 0,
+            backend,
+        }
+    }
+
+    /// Feeds more data into the hasher.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - A byte slice containing the next piece of the message.
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+
+        // Top up a previously buffered partial block first
+        if self.buffer_len > 0 {
+            let take = (64 - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+
+            if self.buffer_len < 64 {
+                return;
+            }
+            self.backend.compress_blocks(&mut self.state, &self.buffer);
+            self.buffer_len = 0;
+        }
+
+        // Compress full blocks straight from the input
+        let full = data.len() - data.len() % 64;
+        self.backend.compress_blocks(&mut self.state, &data[..full]);
+
+        // Keep the remainder for the next call
+        let rest = &data[full..];
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffer_len = rest.len();
+    }
+
+    /// Pads the buffered tail and returns the final hash.
+    ///
+    /// The padding appends a single '1' bit, '0' bits until the length is 448
+    /// modulo 512, and the original message length as a 64-bit big-endian
+    /// integer. Only the last one or two blocks are ever padded.
+    ///
+    /// # Returns
+    ///
+    /// A 32-byte array containing the SHA-256 hash of all data passed to `update`.
+    pub fn finalize(mut self) -> [u8; 32] {
+        let bit_length = self.length.wrapping_mul(8);
+
+        // Step 1: Append a single '1' bit (0x80 in hexadecimal)
+        self.buffer[self.buffer_len] = 0x80;
+        self.buffer[self.buffer_len + 1..].fill(0);
+
+        // Step 2: If there is no room left for the length, flush an extra block
+        if self.buffer_len >= 56 {
+            self.backend.compress_blocks(&mut self.state, &self.buffer);
+            self.buffer = [0u8; 64];
+        }
+
+        // Step 3: Append the original message length as a 64-bit big-endian integer
+        self.buffer[56..].copy_from_slice(&bit_length.to_be_bytes());
+        self.backend.compress_blocks(&mut self.state, &self.buffer);
+
+        // Convert the final hash values into a byte array
+        let mut hash = [0u8; 32];
+        for (chunk, val) in hash.chunks_exact_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&val.to_be_bytes());
+        }
+
+        hash
+    }
+
+    /// Returns the number of bytes passed to `update` so far.
+    pub fn total_len(&self) -> u64 {
+        self.length
+    }
+
+    /// Serializes the midstate so hashing can be resumed later.
+    ///
+    /// The layout is the 8 state words as big-endian `u32`s, the total
+    /// length as a big-endian `u64`, then the 64-byte block buffer, of which
+    /// only the first `total_len() % 64` bytes are used and the rest is zero.
+    ///
+    /// # Returns
+    ///
+    /// A `STATE_LEN`-byte array accepted by `import_state`.
+    pub fn export_state(&self) -> [u8; STATE_LEN] {
+        let mut bytes = [0u8; STATE_LEN];
+        for (chunk, val) in bytes[..32].chunks_exact_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&val.to_be_bytes());
+        }
+        bytes[32..40].copy_from_slice(&self.length.to_be_bytes());
+        bytes[40..40 + self.buffer_len].copy_from_slice(&self.buffer[..self.buffer_len]);
+        bytes
+    }
+
+    /// Restores a hasher from a midstate produced by `export_state`.
+    ///
+    /// Feeding the rest of the message into the restored hasher gives the
+    /// same hash as hashing the whole message at once. Resuming from the
+    /// stored state of a file's earlier contents therefore also shows that
+    /// the file only grew: the result only matches if the old bytes are
+    /// still its prefix.
+    ///
+    /// # Returns
+    ///
+    /// The restored hasher, or `None` if the bytes are not a valid midstate.
+    pub fn import_state(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != STATE_LEN {
+            return None;
+        }
+
+        let mut state = [0u32; 8];
+        for (val, chunk) in state.iter_mut().zip(bytes[..32].chunks_exact(4)) {
+            *val = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        let mut length = [0u8; 8];
+        length.copy_from_slice(&bytes[32..40]);
+        let length = u64::from_be_bytes(length);
+
+        // Unused buffer bytes must be zero so every state has one encoding
+        let buffer_len = (length % 64) as usize;
+        if bytes[40 + buffer_len..].iter().any(|&byte| byte != 0) {
+            return None;
+        }
+        let mut buffer = [0u8; 64];
+        buffer.copy_from_slice(&bytes[40..]);
+
+        Some(Sha256 {
+            state,
+            buffer,
+            buffer_len,
+            length,
+            backend: Backend::detect(),
+        })
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lets `io::copy` and other writers stream data straight into the hasher.
+#[cfg(feature = "std")]
+impl io::Write for Sha256 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Streaming SHA-224 hasher, sharing the SHA-256 compression function.
+#[derive(Clone)]
+pub struct Sha224(Sha256);
+
+impl Sha224 {
+    /// Creates a hasher with the initial SHA-224 state.
+    pub fn new() -> Self {
+        Sha224(Sha256::with_iv(H0_224, Backend::detect()))
+    }
+
+    /// Feeds more data into the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Pads the buffered tail and returns the final, truncated hash.
+    pub fn finalize(self) -> [u8; 28] {
+        let mut hash = [0u8; 28];
+        hash.copy_from_slice(&self.0.finalize()[..28]);
+        hash
+    }
+}
+
+impl Default for Sha224 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl io::Write for Sha224 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Round constants for SHA-256
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5,
+    0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc,
+    0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+    0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3,
+    0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5,
+    0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
 /// Prepares the message schedule for the compression function.
 ///
 /// This function expands the 512-bit block into a series of 64 32-bit words
@@ -78,38 +523,25 @@ fn pad_message(message: &[u8]) -> Vec<u8> {
 /// # Returns
 ///
 /// An array of 64 `u32` words used in the compression function.
-fn message_schedule(block: &[u8]) -> [u32; 64] {
-    //let mut w = [0u32; 64];
-    // Use Arc and Mutex to allow safe concurrent mutation of the array
-    let w = Arc::new(Mutex::new([0u32; 64]));
+const fn message_schedule(block: &[u8]) -> [u32; 64] {
+    let mut w = [0u32; 64];
 
     // Initialize the first 16 words
-    //for i in 0..16 {
-    //    //w[i] = u32::from_be_bytes([block[4 * i], block[4 * i + 1], block[4 * i + 2], block[4 * i + 3]]);
-    //    w[i] = (block[4 * i] as u32) << 24
-    //        | (block[4 * i + 1] as u32) << 16
-    //        | (block[4 * i + 2] as u32) << 8
-    //        | (block[4 * i + 3] as u32);
-    //}
-    (0..16).into_par_iter().for_each(|i| {
-        let mut w = w.lock().unwrap();
-        w[i] = (block[4 * i] as u32) << 24
-            | (block[4 * i + 1] as u32) << 16
-            | (block[4 * i + 2] as u32) << 8
-            | (block[4 * i + 3] as u32);
-    });
-    
-    // Convert Arc<Mutex<[u32; 64]>> back to [u32; 64]
-    let mut w = Arc::try_unwrap(w).unwrap().into_inner().unwrap();
+    let mut i = 0;
+    while i < 16 {
+        w[i] = u32::from_be_bytes([block[4 * i], block[4 * i + 1], block[4 * i + 2], block[4 * i + 3]]);
+        i += 1;
+    }
 
     // Compute the remaining words
-    for i in 16..64 {
+    while i < 64 {
         let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
         let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
         w[i] = w[i - 16]
             .wrapping_add(s0)
             .wrapping_add(w[i - 7])
             .wrapping_add(s1);
+        i += 1;
     }
 
     w
@@ -121,29 +553,12 @@ fn message_schedule(block: &[u8]) -> [u32; 64] {
 ///
 /// # Arguments
 ///
-/// * `h` - A mutable reference to an array of 8 `u32` values representing the current hash state.
+/// * `hash_state` - A mutable reference to an array of 8 `u32` values representing the current hash state.
 /// * `block` - A 64-byte slice representing a 512-bit block of the padded message.
-fn compress(hash_state: &mut [u32; 8], block: &[u8]) {
-    // Constants for SHA-256
-    const K: [u32; 64] = [
-        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5,
-        0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
-        0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
-        0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
-        0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc,
-        0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
-        0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
-        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
-        0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
-        0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
-        0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3,
-        0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
-        0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5,
-        0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
-        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
-        0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
-    ];
-
+///
+/// This is a `const fn` so `sha256_const` can share it; loops are written
+/// with `while` for that reason.
+const fn compress(hash_state: &mut [u32; 8], block: &[u8]) {
     let mut a = hash_state[0];
     let mut b = hash_state[1];
     let mut c = hash_state[2];
@@ -156,7 +571,8 @@ fn compress(hash_state: &mut [u32; 8], block: &[u8]) {
     let w = message_schedule(block);
 
     // Perform 64 rounds of hashing
-    for i in 0..64 {
+    let mut i = 0;
+    while i < 64 {
         let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
         let ch = (e & f) ^ ((!e) & g);
         let temp1 = h.wrapping_add(s1)
@@ -175,6 +591,7 @@ fn compress(hash_state: &mut [u32; 8], block: &[u8]) {
         c = b;
         b = a;
         a = temp1.wrapping_add(temp2);
+        i += 1;
     }
 
     // Update the hash state
@@ -188,10 +605,289 @@ fn compress(hash_state: &mut [u32; 8], block: &[u8]) {
     hash_state[7] = hash_state[7].wrapping_add(h);
 }
 
+/// SHA-256 compression using the x86 SHA extensions (`sha256rnds2`,
+/// `sha256msg1` and `sha256msg2`).
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod shani {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    use super::K;
+
+    /// Returns `true` if the running CPU supports every instruction used here.
+    ///
+    /// Without `std` there is no runtime detection, so this only reports the
+    /// features the crate was compiled for.
+    pub(super) fn is_supported() -> bool {
+        #[cfg(feature = "std")]
+        {
+            is_x86_feature_detected!("sha")
+                && is_x86_feature_detected!("sse2")
+                && is_x86_feature_detected!("ssse3")
+                && is_x86_feature_detected!("sse4.1")
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            cfg!(all(
+                target_feature = "sha",
+                target_feature = "sse2",
+                target_feature = "ssse3",
+                target_feature = "sse4.1"
+            ))
+        }
+    }
+
+    /// Computes the next four message schedule words from the previous sixteen.
+    #[inline]
+    #[target_feature(enable = "sha,sse2,ssse3")]
+    unsafe fn schedule(w0: __m128i, w1: __m128i, w2: __m128i, w3: __m128i) -> __m128i {
+        let t1 = _mm_sha256msg1_epu32(w0, w1);
+        let t2 = _mm_alignr_epi8(w3, w2, 4);
+        _mm_sha256msg2_epu32(_mm_add_epi32(t1, t2), w3)
+    }
+
+    /// Compresses a run of whole 64-byte blocks into the hash state.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure `is_supported()` returned `true`.
+    #[target_feature(enable = "sha,sse2,ssse3,sse4.1")]
+    pub(super) unsafe fn compress_blocks(hash_state: &mut [u32; 8], blocks: &[u8]) {
+        // Byte swap mask turning big-endian message words into lanes
+        let mask = _mm_set_epi64x(0x0c0d_0e0f_0809_0a0b, 0x0405_0607_0001_0203);
+
+        // The instructions expect the state as (A, B, E, F) and (C, D, G, H)
+        let state_ptr = hash_state.as_mut_ptr() as *mut __m128i;
+        let dcba = _mm_loadu_si128(state_ptr);
+        let hgfe = _mm_loadu_si128(state_ptr.add(1));
+        let cdab = _mm_shuffle_epi32(dcba, 0xb1);
+        let efgh = _mm_shuffle_epi32(hgfe, 0x1b);
+        let mut abef = _mm_alignr_epi8(cdab, efgh, 8);
+        let mut cdgh = _mm_blend_epi16(efgh, cdab, 0xf0);
+
+        for block in blocks.chunks_exact(64) {
+            let abef_save = abef;
+            let cdgh_save = cdgh;
+
+            let data_ptr = block.as_ptr() as *const __m128i;
+            let mut w = [
+                _mm_shuffle_epi8(_mm_loadu_si128(data_ptr), mask),
+                _mm_shuffle_epi8(_mm_loadu_si128(data_ptr.add(1)), mask),
+                _mm_shuffle_epi8(_mm_loadu_si128(data_ptr.add(2)), mask),
+                _mm_shuffle_epi8(_mm_loadu_si128(data_ptr.add(3)), mask),
+            ];
+
+            // 16 groups of 4 rounds, the message schedule kept in a ring of 4 vectors
+            for i in 0..16 {
+                if i >= 4 {
+                    w[i % 4] = schedule(w[i % 4], w[(i + 1) % 4], w[(i + 2) % 4], w[(i + 3) % 4]);
+                }
+                let k = _mm_loadu_si128(K.as_ptr().add(4 * i) as *const __m128i);
+                let wk = _mm_add_epi32(w[i % 4], k);
+                cdgh = _mm_sha256rnds2_epu32(cdgh, abef, wk);
+                abef = _mm_sha256rnds2_epu32(abef, cdgh, _mm_shuffle_epi32(wk, 0x0e));
+            }
+
+            abef = _mm_add_epi32(abef, abef_save);
+            cdgh = _mm_add_epi32(cdgh, cdgh_save);
+        }
+
+        // Back to (A, B, C, D) and (E, F, G, H)
+        let feba = _mm_shuffle_epi32(abef, 0x1b);
+        let dchg = _mm_shuffle_epi32(cdgh, 0xb1);
+        _mm_storeu_si128(state_ptr, _mm_blend_epi16(feba, dchg, 0xf0));
+        _mm_storeu_si128(state_ptr.add(1), _mm_alignr_epi8(dchg, feba, 8));
+    }
+}
+
+/// Multi-buffer SHA-256 using AVX2: 8 independent messages are hashed at
+/// once, each one in its own 32-bit lane.
+#[cfg(all(feature = "alloc", any(target_arch = "x86", target_arch = "x86_64")))]
+mod avx2 {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    use super::{H0, K};
+
+    /// Number of messages hashed in parallel.
+    pub(super) const LANES: usize = 8;
+
+    /// Returns `true` if the running CPU supports AVX2.
+    pub(super) fn is_supported() -> bool {
+        #[cfg(feature = "std")]
+        {
+            is_x86_feature_detected!("avx2")
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            cfg!(target_feature = "avx2")
+        }
+    }
+
+    /// A message viewed as a sequence of padded 64-byte blocks.
+    ///
+    /// Whole blocks are borrowed from the message; only the last one or two
+    /// blocks, which carry the padding, are copied.
+    struct Padded<'a> {
+        data: &'a [u8],
+        full_blocks: usize,
+        tail: [u8; 128],
+        tail_blocks: usize,
+    }
+
+    impl<'a> Padded<'a> {
+        fn new(data: &'a [u8]) -> Self {
+            let full_blocks = data.len() / 64;
+            let rest = &data[full_blocks * 64..];
+
+            let mut tail = [0u8; 128];
+            tail[..rest.len()].copy_from_slice(rest);
+            tail[rest.len()] = 0x80;
+            let tail_blocks = if rest.len() >= 56 { 2 } else { 1 };
+            let bit_length = (data.len() as u64).wrapping_mul(8);
+            tail[tail_blocks * 64 - 8..tail_blocks * 64].copy_from_slice(&bit_length.to_be_bytes());
+
+            Padded { data, full_blocks, tail, tail_blocks }
+        }
+
+        fn block_count(&self) -> usize {
+            self.full_blocks + self.tail_blocks
+        }
+
+        fn block(&self, index: usize) -> &[u8] {
+            if index < self.full_blocks {
+                &self.data[index * 64..(index + 1) * 64]
+            } else {
+                let index = index - self.full_blocks;
+                &self.tail[index * 64..(index + 1) * 64]
+            }
+        }
+    }
+
+    macro_rules! rotr {
+        ($x:expr, $n:literal) => {
+            _mm256_or_si256(_mm256_srli_epi32::<$n>($x), _mm256_slli_epi32::<{ 32 - $n }>($x))
+        };
+    }
+
+    /// Runs the 64 SHA-256 rounds on 8 lanes at once.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn compress(state: &mut [__m256i; 8], w: &mut [__m256i; 64]) {
+        for i in 16..64 {
+            let s0 = _mm256_xor_si256(
+                _mm256_xor_si256(rotr!(w[i - 15], 7), rotr!(w[i - 15], 18)),
+                _mm256_srli_epi32::<3>(w[i - 15]),
+            );
+            let s1 = _mm256_xor_si256(
+                _mm256_xor_si256(rotr!(w[i - 2], 17), rotr!(w[i - 2], 19)),
+                _mm256_srli_epi32::<10>(w[i - 2]),
+            );
+            w[i] = _mm256_add_epi32(_mm256_add_epi32(w[i - 16], s0), _mm256_add_epi32(w[i - 7], s1));
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+
+        for (k, wi) in K.iter().zip(w.iter()) {
+            let s1 = _mm256_xor_si256(_mm256_xor_si256(rotr!(e, 6), rotr!(e, 11)), rotr!(e, 25));
+            let ch = _mm256_xor_si256(_mm256_and_si256(e, f), _mm256_andnot_si256(e, g));
+            let temp1 = _mm256_add_epi32(
+                _mm256_add_epi32(h, s1),
+                _mm256_add_epi32(ch, _mm256_add_epi32(_mm256_set1_epi32(*k as i32), *wi)),
+            );
+            let s0 = _mm256_xor_si256(_mm256_xor_si256(rotr!(a, 2), rotr!(a, 13)), rotr!(a, 22));
+            let maj = _mm256_xor_si256(
+                _mm256_xor_si256(_mm256_and_si256(a, b), _mm256_and_si256(a, c)),
+                _mm256_and_si256(b, c),
+            );
+            let temp2 = _mm256_add_epi32(s0, maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = _mm256_add_epi32(d, temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = _mm256_add_epi32(temp1, temp2);
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = _mm256_add_epi32(*word, value);
+        }
+    }
+
+    /// Hashes up to `LANES` messages, writing one hash per message to `out`.
+    ///
+    /// Lanes whose message has run out of blocks keep computing on a dummy
+    /// block, but their state is masked back to the saved value.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure `is_supported()` returned `true`.
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn hash_lanes(messages: &[&[u8]], out: &mut [[u8; 32]]) {
+        debug_assert!(messages.len() <= LANES && messages.len() == out.len());
+
+        let padded: [Padded; LANES] =
+            core::array::from_fn(|lane| Padded::new(messages.get(lane).copied().unwrap_or(&[])));
+        let block_counts: [usize; LANES] = core::array::from_fn(|lane| padded[lane].block_count());
+        let max_blocks = block_counts[..messages.len()].iter().copied().max().unwrap_or(0);
+
+        let mut state: [__m256i; 8] = core::array::from_fn(|i| _mm256_set1_epi32(H0[i] as i32));
+        let mut w = [_mm256_setzero_si256(); 64];
+
+        for index in 0..max_blocks {
+            // Transpose word t of every lane's block into vector t
+            let mut words = [[0u32; LANES]; 16];
+            let mut active = [0i32; LANES];
+            for lane in 0..LANES {
+                if index >= block_counts[lane] {
+                    continue;
+                }
+                active[lane] = -1;
+                let block = padded[lane].block(index);
+                for (t, bytes) in block.chunks_exact(4).enumerate() {
+                    words[t][lane] = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                }
+            }
+            for (wt, lanes) in w.iter_mut().zip(words.iter()) {
+                *wt = _mm256_loadu_si256(lanes.as_ptr() as *const __m256i);
+            }
+
+            let saved = state;
+            compress(&mut state, &mut w);
+
+            let mask = _mm256_loadu_si256(active.as_ptr() as *const __m256i);
+            for (word, old) in state.iter_mut().zip(saved) {
+                *word = _mm256_blendv_epi8(old, *word, mask);
+            }
+        }
+
+        // Transpose the lanes back into one big-endian hash per message
+        let mut words = [[0u32; LANES]; 8];
+        for (lanes, word) in words.iter_mut().zip(state) {
+            _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, word);
+        }
+        for (lane, hash) in out.iter_mut().enumerate() {
+            for (chunk, lanes) in hash.chunks_exact_mut(4).zip(words.iter()) {
+                chunk.copy_from_slice(&lanes[lane].to_be_bytes());
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "alloc")]
     use crate::utility::bytes_to_hex;
+
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_sha256_hello_world() {
         let input = b"hello world";
@@ -201,6 +897,7 @@ mod tests {
         assert_eq!(output_hex, expected_hex);
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_sha256_empty_string() {
         let input = b"";
@@ -210,6 +907,7 @@ mod tests {
         assert_eq!(output_hex, expected_hex);
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
     fn test_sha256_single_character() {
         let input = b"a";
@@ -218,4 +916,124 @@ mod tests {
         let output_hex = bytes_to_hex(&hash);
         assert_eq!(output_hex, expected_hex);
     }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_sha256_two_blocks() {
+        let input = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        let expected_hex = "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1";
+        let hash = sha256(input);
+        let output_hex = bytes_to_hex(&hash);
+        assert_eq!(output_hex, expected_hex);
+    }
+
+    #[test]
+    fn test_sha256_streaming_matches_one_shot() {
+        let input: [u8; 1000] = core::array::from_fn(|i| (i % 251) as u8);
+        let expected = sha256(&input);
+
+        for piece_len in [1, 7, 55, 56, 63, 64, 65, 200] {
+            let mut hasher = Sha256::new();
+            for piece in input.chunks(piece_len) {
+                hasher.update(piece);
+            }
+            assert_eq!(hasher.finalize(), expected, "piece length {}", piece_len);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_sha256_hash_reader() {
+        use std::io::Write;
+
+        // Larger than the copy buffer, and not a multiple of the block size
+        let input: Vec<u8> = (0..100_003u32).map(|i| (i % 251) as u8).collect();
+        let expected = sha256(&input);
+
+        assert_eq!(hash_reader(input.as_slice()).unwrap(), expected);
+
+        let mut hasher = Sha256::new();
+        hasher.write_all(&input).unwrap();
+        assert_eq!(hasher.finalize(), expected);
+    }
+
+    #[test]
+    fn test_sha256_backends_agree() {
+        let input: [u8; 4096] = core::array::from_fn(|i| (i * 31 % 256) as u8);
+
+        for len in [0, 1, 55, 56, 64, 119, 128, 1000, 4096] {
+            let expected = portable_sha256(&input[..len]);
+            assert_eq!(sha256(&input[..len]), expected, "length {}", len);
+            if let Some(hash) = simd_sha256(&input[..len]) {
+                assert_eq!(hash, expected, "length {}", len);
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_sha256_many_matches_sha256() {
+        let data: Vec<u8> = (0..2000u32).map(|i| (i * 7 % 256) as u8).collect();
+        let lengths = [0, 3, 55, 56, 64, 64, 64, 200, 1000, 119, 120, 2000, 1, 64, 64, 64, 9];
+        let inputs: Vec<&[u8]> = lengths.iter().map(|&len| &data[..len]).collect();
+
+        let hashes = sha256_many(&inputs);
+
+        assert_eq!(hashes.len(), inputs.len());
+        for (input, hash) in inputs.iter().zip(hashes.iter()) {
+            assert_eq!(*hash, sha256(input), "length {}", input.len());
+        }
+        assert!(sha256_many(&[]).is_empty());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_sha224() {
+        assert_eq!(
+            bytes_to_hex(&sha224(b"abc")),
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7"
+        );
+        assert_eq!(
+            bytes_to_hex(&sha224(b"")),
+            "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f"
+        );
+    }
+
+    #[test]
+    fn test_sha256_resume_from_midstate() {
+        let input: [u8; 1000] = core::array::from_fn(|i| (i % 251) as u8);
+
+        for split in [0, 1, 63, 64, 100, 999] {
+            let mut hasher = Sha256::new();
+            hasher.update(&input[..split]);
+            let saved = hasher.export_state();
+
+            let mut resumed = Sha256::import_state(&saved).unwrap();
+            assert_eq!(resumed.total_len(), split as u64);
+            resumed.update(&input[split..]);
+            assert_eq!(resumed.finalize(), sha256(&input), "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_sha256_import_rejects_invalid_state() {
+        let mut hasher = Sha256::new();
+        hasher.update(b"abc");
+        let mut saved = hasher.export_state();
+
+        assert!(Sha256::import_state(&saved[..STATE_LEN - 1]).is_none());
+        saved[STATE_LEN - 1] = 1;
+        assert!(Sha256::import_state(&saved).is_none());
+    }
+
+    #[test]
+    fn test_sha256_const() {
+        const ABC: [u8; 32] = sha256_const(b"abc");
+        assert_eq!(ABC, sha256(b"abc"));
+
+        let input: [u8; 1000] = core::array::from_fn(|i| (i % 251) as u8);
+        for len in [0, 1, 55, 56, 63, 64, 119, 120, 128, 1000] {
+            assert_eq!(sha256_const(&input[..len]), sha256(&input[..len]), "length {}", len);
+        }
+    }
 }
@@ -0,0 +1,382 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Computes the SHA3-224 hash of the input data.
+pub fn sha3_224(input: &[u8]) -> [u8; 28] {
+    let mut hasher = Sha3_224::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Computes the SHA3-256 hash of the input data.
+pub fn sha3_256(input: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha3_256::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Computes the SHA3-384 hash of the input data.
+pub fn sha3_384(input: &[u8]) -> [u8; 48] {
+    let mut hasher = Sha3_384::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Computes the SHA3-512 hash of the input data.
+pub fn sha3_512(input: &[u8]) -> [u8; 64] {
+    let mut hasher = Sha3_512::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Fills `output` with the SHAKE128 extendable output of the input data.
+pub fn shake128(input: &[u8], output: &mut [u8]) {
+    let mut hasher = Shake128::new();
+    hasher.update(input);
+    hasher.finalize_xof().read(output);
+}
+
+/// Fills `output` with the SHAKE256 extendable output of the input data.
+pub fn shake256(input: &[u8], output: &mut [u8]) {
+    let mut hasher = Shake256::new();
+    hasher.update(input);
+    hasher.finalize_xof().read(output);
+}
+
+/// Round constants for the iota step
+const RC: [u64; 24] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+/// Rotation offsets for the rho step, in the lane order visited by `PI`
+const RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+/// Lane visiting order for the combined rho and pi steps
+const PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+/// Applies the Keccak-f[1600] permutation to the state.
+///
+/// # Arguments
+///
+/// * `a` - The 5x5 state of 64-bit lanes, indexed as `a[x + 5 * y]`.
+pub fn keccak_f1600(a: &mut [u64; 25]) {
+    for rc in RC {
+        // Theta
+        let mut c = [0u64; 5];
+        for (x, column) in c.iter_mut().enumerate() {
+            *column = a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+
+        // Rho and pi
+        let mut last = a[1];
+        for (&lane, &rotation) in PI.iter().zip(RHO.iter()) {
+            let next = a[lane];
+            a[lane] = last.rotate_left(rotation);
+            last = next;
+        }
+
+        // Chi
+        for y in 0..5 {
+            let mut row = [0u64; 5];
+            row.copy_from_slice(&a[5 * y..5 * y + 5]);
+            for x in 0..5 {
+                a[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // Iota
+        a[0] ^= rc;
+    }
+}
+
+/// Keccak sponge with a byte-oriented absorb and squeeze.
+#[derive(Clone)]
+struct Sponge {
+    state: [u64; 25],
+    rate: usize,
+    pos: usize,
+}
+
+impl Sponge {
+    fn new(rate: usize) -> Self {
+        Sponge { state: [0u64; 25], rate, pos: 0 }
+    }
+
+    fn xor_byte(&mut self, index: usize, byte: u8) {
+        self.state[index / 8] ^= (byte as u64) << (8 * (index % 8));
+    }
+
+    fn absorb(&mut self, mut data: &[u8]) {
+        // Whole blocks are XORed in lane by lane
+        if self.pos == 0 {
+            while data.len() >= self.rate {
+                for (lane, bytes) in self.state.iter_mut().zip(data[..self.rate].chunks_exact(8)) {
+                    let mut le = [0u8; 8];
+                    le.copy_from_slice(bytes);
+                    *lane ^= u64::from_le_bytes(le);
+                }
+                keccak_f1600(&mut self.state);
+                data = &data[self.rate..];
+            }
+        }
+
+        for &byte in data {
+            self.xor_byte(self.pos, byte);
+            self.pos += 1;
+            if self.pos == self.rate {
+                keccak_f1600(&mut self.state);
+                self.pos = 0;
+            }
+        }
+    }
+
+    /// Applies the domain separation suffix and pad10*1, leaving the sponge
+    /// ready to squeeze.
+    fn pad(&mut self, domain: u8) {
+        self.xor_byte(self.pos, domain);
+        self.xor_byte(self.rate - 1, 0x80);
+        keccak_f1600(&mut self.state);
+        self.pos = 0;
+    }
+
+    fn squeeze(&mut self, output: &mut [u8]) {
+        for byte in output {
+            if self.pos == self.rate {
+                keccak_f1600(&mut self.state);
+                self.pos = 0;
+            }
+            *byte = (self.state[self.pos / 8] >> (8 * (self.pos % 8))) as u8;
+            self.pos += 1;
+        }
+    }
+}
+
+/// Domain separation suffix for the SHA-3 hash functions
+const SHA3_DOMAIN: u8 = 0x06;
+
+/// Domain separation suffix for the SHAKE extendable output functions
+const SHAKE_DOMAIN: u8 = 0x1f;
+
+macro_rules! sha3_hasher {
+    ($name:ident, $adapter:ident, $label:literal, $output_len:literal) => {
+        #[doc = concat!("Streaming ", $label, " hasher.")]
+        #[derive(Clone)]
+        pub struct $name(Sponge);
+
+        impl $name {
+            #[doc = concat!("Creates a hasher with the initial ", $label, " state.")]
+            pub fn new() -> Self {
+                // The capacity is twice the output size
+                $name(Sponge::new(200 - 2 * $output_len))
+            }
+
+            /// Feeds more data into the hasher.
+            pub fn update(&mut self, data: &[u8]) {
+                self.0.absorb(data);
+            }
+
+            /// Pads the message and returns the final hash.
+            pub fn finalize(mut self) -> [u8; $output_len] {
+                let mut hash = [0u8; $output_len];
+                self.0.pad(SHA3_DOMAIN);
+                self.0.squeeze(&mut hash);
+                hash
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        #[doc = concat!($label, " adapter for `merkle::HashFunction`.")]
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $adapter;
+
+        #[cfg(feature = "alloc")]
+        impl crate::merkle::HashFunction for $adapter {
+            fn hash(&self, input: &[u8]) -> Vec<u8> {
+                let mut hasher = $name::new();
+                hasher.update(input);
+                hasher.finalize().to_vec()
+            }
+
+            fn algorithm(&self) -> Option<crate::algorithm::HashAlgorithm> {
+                Some(crate::algorithm::HashAlgorithm::$name)
+            }
+        }
+    };
+}
+
+sha3_hasher!(Sha3_224, Sha3_224Hasher, "SHA3-224", 28);
+sha3_hasher!(Sha3_256, Sha3_256Hasher, "SHA3-256", 32);
+sha3_hasher!(Sha3_384, Sha3_384Hasher, "SHA3-384", 48);
+sha3_hasher!(Sha3_512, Sha3_512Hasher, "SHA3-512", 64);
+
+/// Reader for the output of a finalized SHAKE instance.
+///
+/// Successive reads continue the same output stream.
+#[derive(Clone)]
+pub struct XofReader(Sponge);
+
+impl XofReader {
+    /// Fills `output` with the next bytes of the output stream.
+    pub fn read(&mut self, output: &mut [u8]) {
+        self.0.squeeze(output);
+    }
+}
+
+macro_rules! shake_hasher {
+    ($name:ident, $adapter:ident, $label:literal, $security:literal, $default_len:literal) => {
+        #[doc = concat!("Streaming ", $label, " extendable output function.")]
+        #[derive(Clone)]
+        pub struct $name(Sponge);
+
+        impl $name {
+            #[doc = concat!("Creates a ", $label, " instance.")]
+            pub fn new() -> Self {
+                $name(Sponge::new(200 - $security / 4))
+            }
+
+            /// Feeds more data into the function.
+            pub fn update(&mut self, data: &[u8]) {
+                self.0.absorb(data);
+            }
+
+            /// Pads the message and returns a reader for any amount of output.
+            pub fn finalize_xof(mut self) -> XofReader {
+                self.0.pad(SHAKE_DOMAIN);
+                XofReader(self.0)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        #[doc = concat!($label, " adapter for `merkle::HashFunction`, producing `output_len` bytes.")]
+        #[derive(Clone, Copy, Debug)]
+        pub struct $adapter {
+            pub output_len: usize,
+        }
+
+        impl Default for $adapter {
+            #[doc = concat!("Uses ", $default_len, " bytes of output, the full ", $label, " security level.")]
+            fn default() -> Self {
+                $adapter { output_len: $default_len }
+            }
+        }
+
+        #[cfg(feature = "alloc")]
+        impl crate::merkle::HashFunction for $adapter {
+            fn hash(&self, input: &[u8]) -> Vec<u8> {
+                let mut hasher = $name::new();
+                hasher.update(input);
+                let mut output = alloc::vec![0u8; self.output_len];
+                hasher.finalize_xof().read(&mut output);
+                output
+            }
+
+            /// Only the default output length is the `HashAlgorithm` of the same name
+            fn algorithm(&self) -> Option<crate::algorithm::HashAlgorithm> {
+                (self.output_len == $default_len).then_some(crate::algorithm::HashAlgorithm::$name)
+            }
+        }
+    };
+}
+
+shake_hasher!(Shake128, Shake128Hasher, "SHAKE128", 128, 32);
+shake_hasher!(Shake256, Shake256Hasher, "SHAKE256", 256, 64);
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::utility::bytes_to_hex;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_sha3_abc() {
+        assert_eq!(
+            bytes_to_hex(&sha3_224(b"abc")),
+            "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf"
+        );
+        assert_eq!(
+            bytes_to_hex(&sha3_256(b"abc")),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+        assert_eq!(
+            bytes_to_hex(&sha3_384(b"abc")),
+            "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0\
+             e49be4b298d88cea927ac7f539f1edf228376d25"
+        );
+        assert_eq!(
+            bytes_to_hex(&sha3_512(b"abc")),
+            "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e\
+             10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"
+        );
+    }
+
+    #[test]
+    fn test_sha3_streaming_matches_one_shot() {
+        let input: Vec<u8> = (0..500u32).map(|i| (i % 251) as u8).collect();
+        let expected = "495689a003b0b1a4ec4572335ed2d96510cac163d6cc7e83daa73d9b555a2fd5";
+        assert_eq!(bytes_to_hex(&sha3_256(&input)), expected);
+
+        for piece_len in [1, 135, 136, 137, 300] {
+            let mut hasher = Sha3_256::new();
+            for piece in input.chunks(piece_len) {
+                hasher.update(piece);
+            }
+            assert_eq!(bytes_to_hex(&hasher.finalize()), expected, "piece length {}", piece_len);
+        }
+    }
+
+    #[test]
+    fn test_shake() {
+        let mut output = [0u8; 32];
+        shake128(b"", &mut output);
+        assert_eq!(
+            bytes_to_hex(&output),
+            "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26"
+        );
+
+        let mut output = [0u8; 64];
+        shake256(b"", &mut output);
+        assert_eq!(
+            bytes_to_hex(&output),
+            "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762f\
+             d75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be"
+        );
+
+        // Reading in pieces continues the same stream across permutations
+        let mut hasher = Shake128::new();
+        hasher.update(b"abc");
+        let mut reader = hasher.finalize_xof();
+        let mut head = [0u8; 150];
+        let mut tail = [0u8; 50];
+        reader.read(&mut head);
+        reader.read(&mut tail);
+        assert_eq!(
+            bytes_to_hex(&tail[18..]),
+            "6aa01b3f5af057805f973ff8ecb8b226ac32ada6f01c1fcd4818cb006aa5b4cd"
+        );
+    }
+}
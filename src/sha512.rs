@@ -0,0 +1,422 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+/// Computes the SHA-512 hash of the input data.
+///
+/// # Arguments
+///
+/// * `input` - A byte slice containing the data to be hashed.
+///
+/// # Returns
+///
+/// A 64-byte array containing the SHA-512 hash of the input data.
+pub fn sha512(input: &[u8]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Computes the SHA-384 hash of the input data.
+///
+/// # Arguments
+///
+/// * `input` - A byte slice containing the data to be hashed.
+///
+/// # Returns
+///
+/// A 48-byte array containing the SHA-384 hash of the input data.
+pub fn sha384(input: &[u8]) -> [u8; 48] {
+    let mut hasher = Sha384::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// Computes the SHA-512/256 hash of the input data.
+///
+/// This is usually faster than SHA-256 on 64-bit hosts while producing a
+/// digest of the same size.
+///
+/// # Arguments
+///
+/// * `input` - A byte slice containing the data to be hashed.
+///
+/// # Returns
+///
+/// A 32-byte array containing the SHA-512/256 hash of the input data.
+pub fn sha512_256(input: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha512_256::new();
+    hasher.update(input);
+    hasher.finalize()
+}
+
+/// SHA-512 adapter for `merkle::HashFunction`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha512Hasher;
+
+#[cfg(feature = "alloc")]
+impl crate::merkle::HashFunction for Sha512Hasher {
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        sha512(input).to_vec()
+    }
+
+    fn algorithm(&self) -> Option<crate::algorithm::HashAlgorithm> {
+        Some(crate::algorithm::HashAlgorithm::Sha512)
+    }
+}
+
+/// SHA-384 adapter for `merkle::HashFunction`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha384Hasher;
+
+#[cfg(feature = "alloc")]
+impl crate::merkle::HashFunction for Sha384Hasher {
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        sha384(input).to_vec()
+    }
+
+    fn algorithm(&self) -> Option<crate::algorithm::HashAlgorithm> {
+        Some(crate::algorithm::HashAlgorithm::Sha384)
+    }
+}
+
+/// SHA-512/256 adapter for `merkle::HashFunction`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha512_256Hasher;
+
+#[cfg(feature = "alloc")]
+impl crate::merkle::HashFunction for Sha512_256Hasher {
+    fn hash(&self, input: &[u8]) -> Vec<u8> {
+        sha512_256(input).to_vec()
+    }
+
+    fn algorithm(&self) -> Option<crate::algorithm::HashAlgorithm> {
+        Some(crate::algorithm::HashAlgorithm::Sha512_256)
+    }
+}
+
+/// Initial hash values as defined in the SHA-512 specification
+const H0_512: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+/// Initial hash values as defined in the SHA-384 specification
+const H0_384: [u64; 8] = [
+    0xcbbb9d5dc1059ed8, 0x629a292a367cd507, 0x9159015a3070dd17, 0x152fecd8f70e5939,
+    0x67332667ffc00b31, 0x8eb44a8768581511, 0xdb0c2e0d64f98fa7, 0x47b5481dbefa4fa4,
+];
+
+/// Initial hash values as defined in the SHA-512/256 specification
+const H0_512_256: [u64; 8] = [
+    0x22312194fc2bf72c, 0x9f555fa3c84c64c2, 0x2393b86b6f53b151, 0x963877195940eabd,
+    0x96283ee2a88effe3, 0xbe5e1e2553863992, 0x2b0199fc2c85b8aa, 0x0eb72ddc81c52ca2,
+];
+
+/// Round constants for SHA-512
+const K: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+/// Streaming core shared by the SHA-512 family.
+///
+/// Works like `sha256::Sha256`, but on 128-byte blocks of 64-bit words with a
+/// 128-bit message length.
+#[derive(Clone)]
+struct Engine {
+    state: [u64; 8],
+    buffer: [u8; 128],
+    buffer_len: usize,
+    length: u128,
+}
+
+impl Engine {
+    fn new(iv: [u64; 8]) -> Self {
+        Engine {
+            state: iv,
+            buffer: [0u8; 128],
+            buffer_len: 0,
+            length: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u128);
+
+        // Top up a previously buffered partial block first
+        if self.buffer_len > 0 {
+            let take = (128 - self.buffer_len).min(data.len());
+            self.buffer[self.buffer_len..self.buffer_len + take].copy_from_slice(&data[..take]);
+            self.buffer_len += take;
+            data = &data[take..];
+
+            if self.buffer_len < 128 {
+                return;
+            }
+            compress(&mut self.state, &self.buffer);
+            self.buffer_len = 0;
+        }
+
+        // Compress full blocks straight from the input
+        let mut blocks = data.chunks_exact(128);
+        for block in &mut blocks {
+            compress(&mut self.state, block);
+        }
+
+        // Keep the remainder for the next call
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffer_len = rest.len();
+    }
+
+    /// Pads the buffered tail and returns the full 64-byte final state.
+    fn finalize(mut self) -> [u8; 64] {
+        let bit_length = self.length.wrapping_mul(8);
+
+        // Append a single '1' bit, then '0' bits until the length is 896 modulo 1024
+        self.buffer[self.buffer_len] = 0x80;
+        self.buffer[self.buffer_len + 1..].fill(0);
+
+        if self.buffer_len >= 112 {
+            compress(&mut self.state, &self.buffer);
+            self.buffer = [0u8; 128];
+        }
+
+        // Append the original message length as a 128-bit big-endian integer
+        self.buffer[112..].copy_from_slice(&bit_length.to_be_bytes());
+        compress(&mut self.state, &self.buffer);
+
+        let mut hash = [0u8; 64];
+        for (chunk, val) in hash.chunks_exact_mut(8).zip(self.state.iter()) {
+            chunk.copy_from_slice(&val.to_be_bytes());
+        }
+
+        hash
+    }
+}
+
+/// Streaming SHA-512 hasher.
+#[derive(Clone)]
+pub struct Sha512(Engine);
+
+impl Sha512 {
+    /// Creates a hasher with the initial SHA-512 state.
+    pub fn new() -> Self {
+        Sha512(Engine::new(H0_512))
+    }
+
+    /// Feeds more data into the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Pads the buffered tail and returns the final hash.
+    pub fn finalize(self) -> [u8; 64] {
+        self.0.finalize()
+    }
+}
+
+impl Default for Sha512 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streaming SHA-384 hasher.
+#[derive(Clone)]
+pub struct Sha384(Engine);
+
+impl Sha384 {
+    /// Creates a hasher with the initial SHA-384 state.
+    pub fn new() -> Self {
+        Sha384(Engine::new(H0_384))
+    }
+
+    /// Feeds more data into the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Pads the buffered tail and returns the final, truncated hash.
+    pub fn finalize(self) -> [u8; 48] {
+        let mut hash = [0u8; 48];
+        hash.copy_from_slice(&self.0.finalize()[..48]);
+        hash
+    }
+}
+
+impl Default for Sha384 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streaming SHA-512/256 hasher.
+#[derive(Clone)]
+pub struct Sha512_256(Engine);
+
+impl Sha512_256 {
+    /// Creates a hasher with the initial SHA-512/256 state.
+    pub fn new() -> Self {
+        Sha512_256(Engine::new(H0_512_256))
+    }
+
+    /// Feeds more data into the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    /// Pads the buffered tail and returns the final, truncated hash.
+    pub fn finalize(self) -> [u8; 32] {
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&self.0.finalize()[..32]);
+        hash
+    }
+}
+
+impl Default for Sha512_256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Performs the SHA-512 compression function on a single 128-byte block.
+///
+/// # Arguments
+///
+/// * `hash_state` - The current hash state, updated in place.
+/// * `block` - A 128-byte slice representing a 1024-bit block of the padded message.
+fn compress(hash_state: &mut [u64; 8], block: &[u8]) {
+    let mut w = [0u64; 80];
+
+    // Initialize the first 16 words
+    for (word, bytes) in w.iter_mut().zip(block.chunks_exact(8)) {
+        let mut be = [0u8; 8];
+        be.copy_from_slice(bytes);
+        *word = u64::from_be_bytes(be);
+    }
+
+    // Compute the remaining words
+    for i in 16..80 {
+        let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+        let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *hash_state;
+
+    // Perform 80 rounds of hashing
+    for (k, wi) in K.iter().zip(w.iter()) {
+        let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+        let ch = (e & f) ^ ((!e) & g);
+        let temp1 = h.wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(*k)
+            .wrapping_add(*wi);
+        let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let temp2 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(temp1);
+        d = c;
+        c = b;
+        b = a;
+        a = temp1.wrapping_add(temp2);
+    }
+
+    // Update the hash state
+    for (word, value) in hash_state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::utility::bytes_to_hex;
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_sha512() {
+        assert_eq!(
+            bytes_to_hex(&sha512(b"abc")),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        assert_eq!(
+            bytes_to_hex(&sha512(b"")),
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+             47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_sha512_two_blocks() {
+        let input = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmn\
+                      hijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+        assert_eq!(
+            bytes_to_hex(&sha512(input)),
+            "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+             501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_sha384() {
+        assert_eq!(
+            bytes_to_hex(&sha384(b"abc")),
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded163\
+             1a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7"
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_sha512_256() {
+        assert_eq!(
+            bytes_to_hex(&sha512_256(b"abc")),
+            "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23"
+        );
+    }
+
+    #[test]
+    fn test_sha512_streaming_matches_one_shot() {
+        let input: [u8; 1000] = core::array::from_fn(|i| (i % 251) as u8);
+        let expected = sha512(&input);
+
+        for piece_len in [1, 111, 112, 127, 128, 129, 300] {
+            let mut hasher = Sha512::new();
+            for piece in input.chunks(piece_len) {
+                hasher.update(piece);
+            }
+            assert_eq!(hasher.finalize(), expected, "piece length {}", piece_len);
+        }
+    }
+}
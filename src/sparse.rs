@@ -0,0 +1,508 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::mem;
+
+use crate::digest::Digest;
+use crate::merkle::{HashFunction, LEAF_PREFIX, NODE_PREFIX};
+use crate::utility::constant_time_eq;
+
+/// Sparse Merkle tree over every possible key, keyed by the hash of a path
+///
+/// Each path is hashed to a key whose bits, from the most significant, pick
+/// the way down from the root. A leaf is kept as high up as it can be, at the
+/// first node whose subtree holds no other key, and subtrees without keys are
+/// not stored at all: their hashes are precomputed. An update therefore walks
+/// and rehashes about `log2(len)` nodes rather than one per key bit.
+///
+/// Because every path has exactly one place in the tree, a proof can show
+/// that a path is present with a given value, or that it is absent.
+///
+/// Leaves are `H(0x00 || key || value)` and nodes `H(0x01 || left || right)`;
+/// an empty subtree of height 0 is `H("")`. The leaf includes its key, since
+/// its height alone does not say where it is.
+pub struct SparseMerkleTree<H: HashFunction> {
+    hasher: H,
+    /// `empty[h]` is the hash of an empty subtree of height `h`
+    empty: Vec<Vec<u8>>,
+    root: Node,
+    len: usize,
+}
+
+/// A subtree of a `SparseMerkleTree`; a `Branch` always holds at least two keys
+enum Node {
+    Empty,
+    Leaf { key: Digest, value: Vec<u8>, hash: Vec<u8> },
+    Branch { hash: Vec<u8>, children: Box<[Node; 2]> },
+}
+
+/// Proof for one key of a `SparseMerkleTree`
+///
+/// Siblings that are empty subtrees are not stored; a bitmap records which
+/// heights have a sibling in the proof.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SparseProof {
+    /// Height at which the key's path ends, at a leaf or an empty subtree
+    pub height: usize,
+    /// The key and value of the leaf where the path ends, if it holds a
+    /// different key; this shows the proven key is absent
+    pub leaf: Option<(Vec<u8>, Vec<u8>)>,
+    /// Bit `h` (least significant first within each byte) is set if the
+    /// sibling at height `h` is in `siblings`
+    pub bitmap: Vec<u8>,
+    /// The non-empty siblings, from the leaf up
+    pub siblings: Vec<Vec<u8>>,
+}
+
+impl<H: HashFunction> SparseMerkleTree<H> {
+    /// Create an empty tree; its depth is the hasher's output size in bits
+    ///
+    /// The output must fit in a `Digest`.
+    pub fn new(hasher: H) -> Self {
+        let empty = empty_hashes(&hasher);
+        SparseMerkleTree { hasher, empty, root: Node::Empty, len: 0 }
+    }
+
+    /// The number of key bits, and of levels below the root
+    pub fn depth(&self) -> usize {
+        self.empty.len() - 1
+    }
+
+    /// The number of paths in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the tree holds no paths
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The key a path is stored under
+    pub fn key(&self, path: &[u8]) -> Digest {
+        Digest::from_slice(&self.hasher.hash(path)).expect("hash function output fits in a digest")
+    }
+
+    /// The value stored for `path`, if any
+    pub fn get(&self, path: &[u8]) -> Option<&[u8]> {
+        let key = self.key(path);
+        let mut node = &self.root;
+        let mut height = self.depth();
+        loop {
+            match node {
+                Node::Empty => return None,
+                Node::Leaf { key: stored, value, .. } => {
+                    return (*stored == key).then_some(value.as_slice())
+                }
+                Node::Branch { children, .. } => {
+                    node = &children[side(&key, self.depth() - height)];
+                    height -= 1;
+                }
+            }
+        }
+    }
+
+    /// Insert or update the value for `path`, returning the previous value
+    pub fn insert(&mut self, path: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        let key = self.key(path);
+        let mut root = mem::replace(&mut self.root, Node::Empty);
+        let previous = self.insert_at(&mut root, self.depth(), &key, value);
+        self.root = root;
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    /// Delete `path` from the tree, returning its value
+    pub fn remove(&mut self, path: &[u8]) -> Option<Vec<u8>> {
+        let key = self.key(path);
+        let mut root = mem::replace(&mut self.root, Node::Empty);
+        let value = self.remove_at(&mut root, self.depth(), &key);
+        self.root = root;
+        if value.is_some() {
+            self.len -= 1;
+        }
+        value
+    }
+
+    /// Get the root of the tree
+    pub fn root_hash(&self) -> &[u8] {
+        self.root.hash(&self.empty[self.depth()])
+    }
+
+    /// Get the root as a `Digest`, for constant-time comparison
+    pub fn root(&self) -> Digest {
+        Digest::from_slice(self.root_hash()).expect("hash function output fits in a digest")
+    }
+
+    /// Generate a proof for `path`, whether or not it is in the tree
+    ///
+    /// Verify it with the stored value to show the path is present, or with
+    /// `None` to show it is absent.
+    pub fn generate_proof(&self, path: &[u8]) -> SparseProof {
+        let key = self.key(path);
+        let mut bitmap = alloc::vec![0u8; self.depth().div_ceil(8)];
+        let mut siblings = Vec::new();
+        let mut node = &self.root;
+        let mut height = self.depth();
+
+        while let Node::Branch { children, .. } = node {
+            let side = side(&key, self.depth() - height);
+            height -= 1;
+            let sibling = &children[1 - side];
+            if !matches!(sibling, Node::Empty) {
+                bitmap[height / 8] |= 1 << (height % 8);
+                siblings.push(sibling.hash(&self.empty[height]).to_vec());
+            }
+            node = &children[side];
+        }
+        siblings.reverse();
+
+        let leaf = match node {
+            Node::Leaf { key: stored, value, .. } if *stored != key => {
+                Some((stored.as_bytes().to_vec(), value.clone()))
+            }
+            _ => None,
+        };
+
+        SparseProof { height, leaf, bitmap, siblings }
+    }
+
+    /// Verify a proof for `path` against an expected root
+    ///
+    /// With `Some(value)`, checks that the path is in the tree with that
+    /// value; with `None`, checks that the path is not in the tree. Only the
+    /// hasher is needed, not the tree itself.
+    pub fn verify_proof(
+        hasher: &H,
+        path: &[u8],
+        value: Option<&[u8]>,
+        proof: &SparseProof,
+        expected_root: &[u8],
+    ) -> bool {
+        let empty = empty_hashes(hasher);
+        let depth = empty.len() - 1;
+        if proof.height > depth || proof.bitmap.len() != depth.div_ceil(8) {
+            return false;
+        }
+
+        let key = hasher.hash(path);
+        let mut hash = match (value, &proof.leaf) {
+            (Some(value), None) => hash_leaf(hasher, &key, value),
+            (None, None) => empty[proof.height].clone(),
+            // Another key in the subtree where the path ends means it is absent
+            (None, Some((other, other_value)))
+                if other.len() == key.len()
+                    && *other != key
+                    && (0..depth - proof.height)
+                        .all(|index| bit(other, index) == bit(&key, index)) =>
+            {
+                hash_leaf(hasher, other, other_value)
+            }
+            _ => return false,
+        };
+        let mut siblings = proof.siblings.iter();
+
+        let present = |height: usize| proof.bitmap[height / 8] & (1 << (height % 8)) != 0;
+        // Nothing lies below the end of the path
+        if (0..proof.height).any(present) {
+            return false;
+        }
+
+        for (height, empty) in empty.iter().enumerate().take(depth).skip(proof.height) {
+            let sibling = if present(height) {
+                match siblings.next() {
+                    Some(sibling) => sibling.as_slice(),
+                    None => return false,
+                }
+            } else {
+                empty.as_slice()
+            };
+
+            hash = if bit(&key, depth - 1 - height) {
+                hash_node(hasher, sibling, &hash)
+            } else {
+                hash_node(hasher, &hash, sibling)
+            };
+        }
+
+        // Every sibling must have been used
+        siblings.next().is_none() && constant_time_eq(&hash, expected_root)
+    }
+
+    /// Insert `key` below `node`, which sits at `height`, and rehash the way back up
+    fn insert_at(
+        &self,
+        node: &mut Node,
+        height: usize,
+        key: &Digest,
+        value: &[u8],
+    ) -> Option<Vec<u8>> {
+        match node {
+            Node::Empty => {
+                *node = self.leaf(*key, value);
+                None
+            }
+            Node::Leaf { key: stored, .. } if *stored == *key => {
+                match mem::replace(node, self.leaf(*key, value)) {
+                    Node::Leaf { value, .. } => Some(value),
+                    _ => unreachable!(),
+                }
+            }
+            Node::Leaf { key: stored, .. } => {
+                // A second key in this subtree: the leaf moves down under a branch
+                let side = side(stored, self.depth() - height);
+                let mut children = Box::new([Node::Empty, Node::Empty]);
+                children[side] = mem::replace(node, Node::Empty);
+                *node = Node::Branch { hash: Vec::new(), children };
+                self.insert_at(node, height, key, value)
+            }
+            Node::Branch { children, .. } => {
+                let child = &mut children[side(key, self.depth() - height)];
+                let previous = self.insert_at(child, height - 1, key, value);
+                self.rehash(node, height);
+                previous
+            }
+        }
+    }
+
+    /// Remove `key` from below `node`, which sits at `height`, and rehash the way back up
+    fn remove_at(&self, node: &mut Node, height: usize, key: &Digest) -> Option<Vec<u8>> {
+        match node {
+            Node::Empty => None,
+            Node::Leaf { key: stored, .. } if *stored == *key => {
+                match mem::replace(node, Node::Empty) {
+                    Node::Leaf { value, .. } => Some(value),
+                    _ => unreachable!(),
+                }
+            }
+            Node::Leaf { .. } => None,
+            Node::Branch { children, .. } => {
+                let child = &mut children[side(key, self.depth() - height)];
+                let value = self.remove_at(child, height - 1, key)?;
+
+                // A branch left with a single leaf is replaced by that leaf
+                let lone_leaf = match &mut **children {
+                    [Node::Empty, leaf @ Node::Leaf { .. }]
+                    | [leaf @ Node::Leaf { .. }, Node::Empty] => {
+                        Some(mem::replace(leaf, Node::Empty))
+                    }
+                    _ => None,
+                };
+                match lone_leaf {
+                    Some(leaf) => *node = leaf,
+                    None => self.rehash(node, height),
+                }
+                Some(value)
+            }
+        }
+    }
+
+    fn leaf(&self, key: Digest, value: &[u8]) -> Node {
+        let hash = hash_leaf(&self.hasher, key.as_bytes(), value);
+        Node::Leaf { key, value: value.to_vec(), hash }
+    }
+
+    /// Recompute the hash of a branch at `height` from its children
+    fn rehash(&self, node: &mut Node, height: usize) {
+        if let Node::Branch { hash, children } = node {
+            let empty = &self.empty[height - 1];
+            *hash = hash_node(&self.hasher, children[0].hash(empty), children[1].hash(empty));
+        }
+    }
+}
+
+impl Node {
+    /// The hash of this subtree, given the hash of an empty one of the same height
+    fn hash<'a>(&'a self, empty: &'a [u8]) -> &'a [u8] {
+        match self {
+            Node::Empty => empty,
+            Node::Leaf { hash, .. } | Node::Branch { hash, .. } => hash,
+        }
+    }
+}
+
+/// The hashes of empty subtrees, from height 0 up to the root
+fn empty_hashes<H: HashFunction>(hasher: &H) -> Vec<Vec<u8>> {
+    let mut empty = alloc::vec![hasher.hash(&[])];
+    let depth = empty[0].len() * 8;
+    for height in 0..depth {
+        let below = &empty[height];
+        empty.push(hash_node(hasher, below, below));
+    }
+    empty
+}
+
+fn hash_leaf<H: HashFunction>(hasher: &H, key: &[u8], value: &[u8]) -> Vec<u8> {
+    hasher.hash(&[&[LEAF_PREFIX], key, value].concat())
+}
+
+fn hash_node<H: HashFunction>(hasher: &H, left: &[u8], right: &[u8]) -> Vec<u8> {
+    hasher.hash(&[&[NODE_PREFIX], left, right].concat())
+}
+
+/// Bit `index` of `key`, counting from the most significant bit
+fn bit(key: &[u8], index: usize) -> bool {
+    key[index / 8] & (0x80 >> (index % 8)) != 0
+}
+
+/// Which child `key` goes to at a branch `index` levels below the root
+fn side(key: &Digest, index: usize) -> usize {
+    bit(key.as_bytes(), index) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::Sha256Hasher;
+    use alloc::format;
+
+    type Tree = SparseMerkleTree<Sha256Hasher>;
+
+    #[test]
+    fn test_sparse_insert_update_remove() {
+        let mut tree = SparseMerkleTree::new(Sha256Hasher);
+        let empty_root = tree.root_hash().to_vec();
+        assert_eq!(tree.depth(), 256);
+
+        assert_eq!(tree.insert(b"/etc/hosts", b"digest-1"), None);
+        assert_eq!(tree.insert(b"/etc/passwd", b"digest-2"), None);
+        let root = tree.root_hash().to_vec();
+        assert_ne!(root, empty_root);
+
+        // The root only depends on the contents, not the order of inserts
+        let mut other = SparseMerkleTree::new(Sha256Hasher);
+        other.insert(b"/etc/passwd", b"digest-2");
+        other.insert(b"/etc/hosts", b"digest-1");
+        assert_eq!(other.root(), tree.root());
+
+        assert_eq!(tree.insert(b"/etc/hosts", b"digest-3"), Some(b"digest-1".to_vec()));
+        assert_eq!(tree.get(b"/etc/hosts"), Some(b"digest-3".as_slice()));
+        assert_eq!(tree.len(), 2);
+        assert_ne!(tree.root_hash(), root);
+
+        assert_eq!(tree.remove(b"/etc/hosts"), Some(b"digest-3".to_vec()));
+        assert_eq!(tree.remove(b"/etc/hosts"), None);
+        assert_eq!(tree.get(b"/etc/hosts"), None);
+        assert_eq!(tree.remove(b"/etc/passwd"), Some(b"digest-2".to_vec()));
+        assert!(tree.is_empty());
+        assert_eq!(tree.root_hash(), empty_root);
+        assert!(matches!(tree.root, Node::Empty));
+    }
+
+    #[test]
+    fn test_sparse_inclusion_and_non_inclusion_proofs() {
+        let mut tree = SparseMerkleTree::new(Sha256Hasher);
+        for i in 0..20 {
+            tree.insert(format!("/srv/file{}", i).as_bytes(), &[i as u8; 32]);
+        }
+        let root = tree.root_hash().to_vec();
+
+        let proof = tree.generate_proof(b"/srv/file7");
+        assert_eq!(proof.leaf, None);
+        assert!(Tree::verify_proof(&Sha256Hasher, b"/srv/file7", Some(&[7; 32]), &proof, &root));
+        assert!(!Tree::verify_proof(&Sha256Hasher, b"/srv/file7", Some(&[8; 32]), &proof, &root));
+        assert!(!Tree::verify_proof(&Sha256Hasher, b"/srv/file7", None, &proof, &root));
+        assert!(!Tree::verify_proof(&Sha256Hasher, b"/srv/file8", Some(&[7; 32]), &proof, &root));
+
+        // Only the few siblings above the leaf are included
+        assert!(proof.siblings.len() < 10);
+
+        let proof = tree.generate_proof(b"/etc/sudoers.d/evil");
+        assert!(Tree::verify_proof(&Sha256Hasher, b"/etc/sudoers.d/evil", None, &proof, &root));
+        assert!(!Tree::verify_proof(
+            &Sha256Hasher,
+            b"/etc/sudoers.d/evil",
+            Some(b""),
+            &proof,
+            &root
+        ));
+
+        tree.insert(b"/etc/sudoers.d/evil", b"");
+        assert!(!Tree::verify_proof(
+            &Sha256Hasher,
+            b"/etc/sudoers.d/evil",
+            None,
+            &proof,
+            tree.root_hash()
+        ));
+    }
+
+    #[test]
+    fn test_sparse_non_inclusion_ends_at_leaf_or_empty_subtree() {
+        let mut tree = SparseMerkleTree::new(Sha256Hasher);
+        for i in 0..20 {
+            tree.insert(format!("/srv/file{}", i).as_bytes(), &[i as u8; 32]);
+        }
+        let root = tree.root_hash().to_vec();
+
+        let (mut at_leaf, mut at_empty) = (0, 0);
+        for i in 0..64 {
+            let path = format!("/tmp/absent{}", i);
+            let mut proof = tree.generate_proof(path.as_bytes());
+            assert!(Tree::verify_proof(&Sha256Hasher, path.as_bytes(), None, &proof, &root));
+
+            match proof.leaf.take() {
+                Some((key, value)) => {
+                    at_leaf += 1;
+                    // The neighbouring leaf must be the one stored there
+                    proof.leaf = Some((key.clone(), alloc::vec![0xff; 32]));
+                    assert!(!Tree::verify_proof(
+                        &Sha256Hasher,
+                        path.as_bytes(),
+                        None,
+                        &proof,
+                        &root
+                    ));
+                    // and may not be the proven key itself
+                    let own_key = tree.key(path.as_bytes()).as_bytes().to_vec();
+                    proof.leaf = Some((own_key, value));
+                    assert!(!Tree::verify_proof(
+                        &Sha256Hasher,
+                        path.as_bytes(),
+                        None,
+                        &proof,
+                        &root
+                    ));
+                }
+                None => at_empty += 1,
+            }
+        }
+        assert!(at_leaf > 0 && at_empty > 0);
+    }
+
+    #[test]
+    fn test_sparse_tree_stays_compact() {
+        let mut tree = SparseMerkleTree::new(Sha256Hasher);
+        for i in 0..4096u32 {
+            tree.insert(&i.to_le_bytes(), &i.to_be_bytes());
+        }
+        assert_eq!(tree.len(), 4096);
+
+        // Leaves sit near height 256 - log2(4096), so proofs stay short
+        for i in (0..4096u32).step_by(97) {
+            let proof = tree.generate_proof(&i.to_le_bytes());
+            assert!(proof.height > 256 - 32);
+            assert!(proof.siblings.len() <= 32);
+            assert!(Tree::verify_proof(
+                &Sha256Hasher,
+                &i.to_le_bytes(),
+                Some(&i.to_be_bytes()),
+                &proof,
+                tree.root_hash()
+            ));
+        }
+
+        // Removing keys collapses branches back to what inserting the rest gives
+        let mut rest = SparseMerkleTree::new(Sha256Hasher);
+        for i in 0..4096u32 {
+            if i % 3 == 0 {
+                assert_eq!(tree.remove(&i.to_le_bytes()), Some(i.to_be_bytes().to_vec()));
+            } else {
+                rest.insert(&i.to_le_bytes(), &i.to_be_bytes());
+            }
+        }
+        assert_eq!(tree.len(), rest.len());
+        assert_eq!(tree.root(), rest.root());
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "alloc")]
+use alloc::{format, string::String, vec::Vec};
+
 /// Converts a byte array to a hexadecimal string representation.
 ///
 /// # Arguments
@@ -7,6 +10,47 @@
 /// # Returns
 ///
 /// A `String` containing the hexadecimal representation of the input bytes.
+#[cfg(feature = "alloc")]
 pub fn bytes_to_hex(bytes: &[u8]) -> String {
     bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
 }
+
+/// Converts a hexadecimal string back into bytes.
+///
+/// # Arguments
+///
+/// * `hex` - An even-length string of hexadecimal digits, in either case.
+///
+/// # Returns
+///
+/// The decoded bytes, or `None` if the string is not valid hexadecimal.
+#[cfg(feature = "alloc")]
+pub fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    hex.as_bytes()
+        .chunks_exact(2)
+        .map(|pair| {
+            let high = (pair[0] as char).to_digit(16)?;
+            let low = (pair[1] as char).to_digit(16)?;
+            Some((high * 16 + low) as u8)
+        })
+        .collect()
+}
+
+/// Compares two byte slices in constant time.
+///
+/// The running time depends only on the lengths of the inputs, not on where
+/// they differ, so it is safe to use for comparing secret tags and digests.
+///
+/// # Returns
+///
+/// `true` if both slices have the same length and contents.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y));
+    core::hint::black_box(diff) == 0
+}